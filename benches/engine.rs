@@ -0,0 +1,60 @@
+//! Benchmarks of running the sample scripts with each engine.
+
+
+use criterion::{ Criterion, BatchSize };
+use isolang::{ ScriptParser, ScriptRunner, Ins };
+use isolang::runner::Engine;
+use isolang::world::MemoryOutput;
+use isolang::world::cell::U32Cell;
+use std::hint::black_box;
+
+
+/// The sample scripts, with the input given to each.
+const SAMPLES : &[(&str, &[u8], &str,)] = &[
+    ("simple_hello_world", include_bytes!("../samples/simple_hello_world.isolang"), "",),
+    ("simple_save",        include_bytes!("../samples/simple_save.isolang"),        "",),
+    ("echo_one",           include_bytes!("../samples/echo_one.isolang"),           "a",),
+    ("echo",               include_bytes!("../samples/echo.isolang"),               "Hello, World!",),
+    ("echo_forever",       include_bytes!("../samples/echo_forever.isolang"),       "a",)
+];
+
+/// The most steps run of each sample, as some never finish.
+const MAX_STEPS : u64 = 100_000;
+
+
+/// Runs each sample with each engine.
+///
+/// *Note: Each runner is created just before it runs, and dropped after, outside of the timed loop,
+/// so only running is measured, as it would be for a single run.*
+fn samples(c : &mut Criterion) {
+    for (name, src, input,) in SAMPLES {
+        let script = ScriptParser::parse(src.iter().copied().map(Ok)).unwrap();
+        let mut group = c.benchmark_group(*name);
+        for (engine_name, engine,) in [("tree", Engine::Tree,), ("bytecode", Engine::Bytecode,), ("optimized", Engine::Optimized,)] {
+            group.bench_function(engine_name, |b| b.iter_batched_ref(
+                || runner(&script, input, engine),
+                |(runner, output,)| {
+                    let _ = runner.run_for(MAX_STEPS);
+                    black_box(output.take())
+                },
+                BatchSize::PerIteration
+            ));
+        }
+        group.finish();
+    }
+}
+
+/// Creates a runner for `script` with `engine`, with dumps turned off.
+fn runner(script : &[Ins], input : &str, engine : Engine) -> (ScriptRunner<U32Cell>, MemoryOutput,) {
+    let (mut runner, output,) = ScriptRunner::<U32Cell>::in_memory(script.to_vec(), input);
+    runner.set_engine(engine);
+    runner.set_dump_output(None);
+    (runner, output,)
+}
+
+
+fn main() {
+    let mut c = Criterion::default().configure_from_args();
+    samples(&mut c);
+    c.final_summary();
+}
@@ -0,0 +1,47 @@
+//! Benchmarks of running the sample scripts with each kind of world storage.
+
+
+use criterion::Criterion;
+use isolang::{ ScriptParser, ScriptRunner };
+use isolang::world::WorldStorage;
+use isolang::world::cell::U32Cell;
+use std::hint::black_box;
+
+
+/// The sample scripts, with the input given to each.
+const SAMPLES : &[(&str, &[u8], &str,)] = &[
+    ("simple_hello_world", include_bytes!("../samples/simple_hello_world.isolang"), "",),
+    ("simple_save",        include_bytes!("../samples/simple_save.isolang"),        "",),
+    ("echo_one",           include_bytes!("../samples/echo_one.isolang"),           "a",),
+    ("echo",               include_bytes!("../samples/echo.isolang"),               "Hello, World!",),
+    ("echo_forever",       include_bytes!("../samples/echo_forever.isolang"),       "a",)
+];
+
+/// The most steps run of each sample, as some never finish.
+const MAX_STEPS : u64 = 100_000;
+
+
+/// Runs each sample with each kind of world storage.
+fn samples(c : &mut Criterion) {
+    for (name, src, input,) in SAMPLES {
+        let script = ScriptParser::parse(src.iter().copied().map(Ok)).unwrap();
+        let mut group = c.benchmark_group(*name);
+        for (storage_name, storage,) in [("chunked", WorldStorage::Chunked,), ("sparse", WorldStorage::Sparse,)] {
+            group.bench_function(storage_name, |b| b.iter(|| {
+                let (mut runner, output,) = ScriptRunner::<U32Cell>::in_memory(script.clone(), *input);
+                runner.world_mut().set_storage(storage);
+                runner.set_dump_output(None);
+                let _ = runner.run_for(MAX_STEPS);
+                black_box(output.take())
+            }));
+        }
+        group.finish();
+    }
+}
+
+
+fn main() {
+    let mut c = Criterion::default().configure_from_args();
+    samples(&mut c);
+    c.final_summary();
+}
@@ -0,0 +1,465 @@
+//! Control-flow graph of a script, with jump targets resolved by constant propagation.
+
+
+use crate::analysis::fixpoint::{ self, AbstractWorld, AbstractExec, Flow, Fixpoint };
+use crate::runner::ins::Ins;
+use crate::world::{ Cell, Adj, Coord, Dir, ArithOp, ArithMode };
+use std::collections::{ BTreeMap, BTreeSet };
+use core::fmt;
+
+
+/// Where control can go after an instruction.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Target {
+
+    /// The instruction at an index.
+    Ins(usize),
+
+    /// The end of the script, where it finishes.
+    End,
+
+    /// Anywhere, as the distance of a jump is not known.
+    Unknown
+
+}
+
+
+/// Something found by a [`ControlFlowGraph`].
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum CfgDiagnostic {
+
+    /// The distance of a jump is not known before running the script.
+    UnresolvedJump {
+        /// The index of the instruction containing the jump.
+        index : usize
+    },
+
+    /// A jump can land before the start, or past the end, of the script.
+    ///
+    /// *Note: A jump before the start goes to the first instruction, and a jump past the end finishes the script.
+    /// Inside a condition or random choice, the script head then moves on by one.*
+    JumpOutOfScript {
+        /// The index of the instruction containing the jump.
+        index  : usize,
+        /// Where the jump lands.
+        target : isize
+    },
+
+    /// A range of instructions which can never run.
+    Unreachable {
+        /// The index of the first instruction.
+        start : usize,
+        /// The index after the last instruction.
+        end   : usize
+    }
+
+}
+
+impl CfgDiagnostic {
+
+    /// Get the indices of the instructions this is about.
+    pub fn indices(&self) -> (usize, usize,) { match (self) {
+        Self::UnresolvedJump { index } | Self::JumpOutOfScript { index, .. } => (*index, *index + 1,),
+        Self::Unreachable { start, end } => (*start, *end,)
+    } }
+
+    /// Returns `true` if this is only informational, and does not point out a likely mistake.
+    pub fn is_note(&self) -> bool { matches!(self, Self::UnresolvedJump { .. }) }
+
+}
+
+impl fmt::Display for CfgDiagnostic {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::UnresolvedJump { .. } => write!(f, "the distance of this jump is not known before running"),
+        Self::JumpOutOfScript { target, .. } => write!(f, "this jump can land outside of the script, at instruction {target}"),
+        Self::Unreachable { start, end } if (end - start == 1) => write!(f, "instruction {start} can never run"),
+        Self::Unreachable { start, end } => write!(f, "instructions {start} to {} can never run", end - 1)
+    } }
+}
+
+
+/// The control-flow graph of a script.
+///
+/// The world is followed through the script using constant propagation, so that the distance
+/// of a [`Ins::JumpThruCode`] is known wherever the cell at the world head is.
+/// Values which come from input or random choices are not known.
+///
+/// *Note: An unresolved jump may land anywhere, so while any is reachable, every instruction is reachable.*
+pub struct ControlFlowGraph {
+
+    /// Where control can go after each instruction.
+    successors  : Vec<BTreeSet<Target>>,
+
+    /// Whether each instruction can run.
+    reachable   : Vec<bool>,
+
+    /// What was found, in script order.
+    diagnostics : Vec<CfgDiagnostic>
+
+}
+
+impl ControlFlowGraph {
+
+    /// Builds the control-flow graph of a script, run with cells of type `C` under `arith_mode`.
+    pub fn build<C : Cell>(script : &[Ins], arith_mode : ArithMode) -> Self {
+        let Fixpoint { states, successors, jumps, unresolved } = fixpoint::solve(script, &mut ConstExec { arith_mode }, ConstWorld::<C>::new());
+
+        let reachable = states.iter().map(Option::is_some).collect::<Vec<_>>();
+        let mut diagnostics = Vec::new();
+        for index in unresolved {
+            diagnostics.push(CfgDiagnostic::UnresolvedJump { index });
+        }
+        for (index, targets,) in jumps {
+            let len = script.len() as isize;
+            if let Some(target) = targets.into_iter().find(|target| *target < 0 || *target > len) {
+                diagnostics.push(CfgDiagnostic::JumpOutOfScript { index, target });
+            }
+        }
+        let mut index = 0;
+        while (index < reachable.len()) {
+            if (reachable[index]) { index += 1; continue; }
+            let start = index;
+            while (index < reachable.len() && ! reachable[index]) { index += 1; }
+            diagnostics.push(CfgDiagnostic::Unreachable { start, end : index });
+        }
+        diagnostics.sort_by_key(CfgDiagnostic::indices);
+
+        Self { successors, reachable, diagnostics }
+    }
+
+    /// Get where control can go after the instruction at `index`.
+    ///
+    /// *Note: Unreachable instructions have no successors.*
+    pub fn successors(&self, index : usize) -> &BTreeSet<Target> { &self.successors[index] }
+
+    /// Returns `true` if the instruction at `index` can run.
+    pub fn is_reachable(&self, index : usize) -> bool { self.reachable[index] }
+
+    /// Get everything found, in script order.
+    pub fn diagnostics(&self) -> &[CfgDiagnostic] { &self.diagnostics }
+
+}
+
+
+/// What is known about the value of a cell.
+#[derive(PartialEq, Clone, Debug)]
+enum Value<C : Cell> {
+    /// The value is always this.
+    Known(C),
+    /// The value is not known.
+    Unknown
+}
+
+impl<C : Cell> Value<C> {
+
+    /// Gets what is known about a value which is either this or `other`.
+    fn join(&self, other : &Self) -> Self {
+        if (self == other) { self.clone() } else { Self::Unknown }
+    }
+
+}
+
+
+/// What is known about a world at some point in a script.
+#[derive(PartialEq, Clone, Debug)]
+struct ConstWorld<C : Cell> {
+
+    /// The position of the world head, if known.
+    head  : Option<Coord>,
+
+    /// Cells which differ from [`Self::rest`]. The origin is never stored.
+    cells : BTreeMap<Coord, Value<C>>,
+
+    /// Every other cell.
+    rest  : Value<C>
+
+}
+
+impl<C : Cell> ConstWorld<C> {
+
+    /// Creates the world a script starts on.
+    fn new() -> Self { Self {
+        head  : Some(Coord::ZERO),
+        cells : BTreeMap::new(),
+        rest  : Value::Known(C::ONE)
+    } }
+
+    /// Get the two cells targeted by an adj, if the head is known.
+    fn adj(&self, adj : Adj) -> (Option<Coord>, Option<Coord>,) {
+        match (self.head) {
+            Some(head) => {
+                let (l, r,) = head + adj;
+                (Some(l), Some(r),)
+            },
+            None => (None, None,)
+        }
+    }
+
+    /// Moves the world head by `offset`, or `None` if the offset itself overflowed.
+    ///
+    /// ### Returns
+    /// Returns `false` if the head certainly leaves the range allowed by [`Coord::MAX_DISTANCE`].
+    fn move_by(&mut self, offset : Option<Coord>) -> bool {
+        let Some(offset) = offset
+            else { return false; };
+        match (self.head) {
+            Some(head) => match (head.checked_add(offset).filter(Coord::in_range)) {
+                Some(head) => {
+                    self.head = Some(head);
+                    true
+                },
+                None => false
+            },
+            None => true
+        }
+    }
+
+    /// Get what is known about a cell, which may be at an unknown position.
+    fn read(&self, coord : Option<Coord>) -> Value<C> {
+        let (Some(coord), Some(head),) = (coord, self.head,)
+            else { return Value::Unknown; };
+        if (coord == Coord::ZERO) {
+            if (head.half_side() == Some(Dir::R)) { Value::Unknown }
+            else { Value::Known(C::ONE) }
+        } else {
+            self.cells.get(&coord).unwrap_or(&self.rest).clone()
+        }
+    }
+
+    /// Overwrites a cell, which may be at an unknown position.
+    fn write(&mut self, coord : Option<Coord>, value : Value<C>) {
+        match (coord) {
+            Some(Coord::ZERO) => { },
+            Some(coord) => {
+                if (value == self.rest) { self.cells.remove(&coord); }
+                else { self.cells.insert(coord, value); }
+            },
+            None => {
+                self.cells.clear();
+                self.rest = Value::Unknown;
+            }
+        }
+    }
+
+}
+
+impl<C : Cell> AbstractWorld for ConstWorld<C> {
+
+    fn forget(&self) -> Self { Self {
+        head  : None,
+        cells : BTreeMap::new(),
+        rest  : Value::Unknown
+    } }
+
+    fn join(&mut self, other : &Self) -> bool {
+        let head = if (self.head == other.head) { self.head } else { None };
+        let rest = self.rest.join(&other.rest);
+        let mut cells = BTreeMap::new();
+        for coord in self.cells.keys().chain(other.cells.keys()) {
+            let value = self.cells.get(coord).unwrap_or(&self.rest)
+                .join(other.cells.get(coord).unwrap_or(&other.rest));
+            if (value != rest) { cells.insert(*coord, value); }
+        }
+        let joined = Self { head, cells, rest };
+        let changed = *self != joined;
+        *self = joined;
+        changed
+    }
+
+}
+
+
+/// Runs instructions on a [`ConstWorld`].
+struct ConstExec {
+
+    /// What happens when the result of an arithmetic instruction does not fit in a cell.
+    arith_mode : ArithMode
+
+}
+
+impl<C : Cell> AbstractExec<ConstWorld<C>> for ConstExec {
+    fn exec(&mut self, index : usize, ins : &Ins, world : ConstWorld<C>, flows : &mut Vec<(Flow, ConstWorld<C>,)>) {
+        self.run(index, ins, world, flows, false);
+    }
+
+}
+
+impl ConstExec {
+
+    /// Runs the instruction at `index`, adding every path through it to `flows`.
+    /// `nested` is set when `ins` is inside a condition or random choice.
+    fn run<C : Cell>(&self, index : usize, ins : &Ins, mut world : ConstWorld<C>, flows : &mut Vec<(Flow, ConstWorld<C>,)>, nested : bool) {
+        match (ins) {
+
+            Ins::MoveHeadOne { adj, dir } => {
+                if (! world.move_by(Some(Coord::from((*adj, *dir,))))) { return; }
+            },
+
+            Ins::MoveHeadDynamic { adj, dir } => match (world.read(world.head)) {
+                Value::Known(cell) => {
+                    if (! world.move_by(Coord::from((*adj, *dir,)).checked_mul(cell.get_isize_val()))) { return; }
+                },
+                Value::Unknown     => { world.head = None; }
+            },
+
+            Ins::Add  { adj } => { if (! self.binop(&mut world, *adj, ArithOp::Add)) { return; } },
+            Ins::Sub  { adj } => { if (! self.binop(&mut world, *adj, ArithOp::Sub)) { return; } },
+            Ins::Mul  { adj } => { if (! self.binop(&mut world, *adj, ArithOp::Mul)) { return; } },
+            Ins::SDiv { adj } => { if (! self.binop(&mut world, *adj, ArithOp::Div)) { return; } },
+
+            Ins::Swap { adj } => {
+                let (l, r,) = world.adj(*adj);
+                let lv = world.read(l);
+                let rv = world.read(r);
+                world.write(l, rv);
+                world.write(r, lv);
+            },
+
+            Ins::Noop | Ins::DumpWorld => { },
+
+            Ins::JumpThruCode { dir } => {
+                let flow = match (world.read(world.head)) {
+                    Value::Known(cell) => {
+                        let distance = cell.get_isize_val();
+                        let distance = match (dir) {
+                            Dir::L => distance.saturating_neg(),
+                            Dir::R => distance
+                        };
+                        Flow::Jump { target : (index as isize).saturating_add(distance), nested }
+                    },
+                    Value::Unknown => Flow::Unresolved
+                };
+                flows.push((flow, world,));
+                return;
+            },
+
+            Ins::IfNotZeroCond { ins } => { self.cond(index, ins, world, flows, false); return; },
+
+            Ins::IfZeroCond { ins } => { self.cond(index, ins, world, flows, true); return; },
+
+            Ins::RandomlyChoose { options } => {
+                self.run(index, &options.0, world.clone(), flows, true);
+                self.run(index, &options.1, world, flows, true);
+                return;
+            }
+
+        }
+        flows.push((Flow::Next, world,));
+    }
+
+    /// Runs an arithmetic instruction.
+    ///
+    /// ### Returns
+    /// Returns `false` if the instruction certainly fails.
+    fn binop<C : Cell>(&self, world : &mut ConstWorld<C>, adj : Adj, op : ArithOp) -> bool {
+        let (l, r,) = world.adj(adj);
+        let value = match (world.read(l), world.read(r),) {
+            (Value::Known(l), Value::Known(r),) => match (l.arith(op, r, self.arith_mode)) {
+                Ok(value) => Value::Known(value),
+                Err(_)    => { return false; }
+            },
+            _ => Value::Unknown
+        };
+        world.write(world.head, value);
+        true
+    }
+
+    /// Runs a conditional instruction, which runs `ins` if the cell at the world head is zero
+    /// when `if_zero` is set, or is not zero otherwise.
+    fn cond<C : Cell>(&self, index : usize, ins : &Ins, world : ConstWorld<C>, flows : &mut Vec<(Flow, ConstWorld<C>,)>, if_zero : bool) {
+        match (world.read(world.head)) {
+            Value::Known(cell) => {
+                if ((cell == C::ZERO) == if_zero) { self.run(index, ins, world, flows, true); }
+                else { flows.push((Flow::Next, world,)); }
+            },
+            Value::Unknown => {
+                self.run(index, ins, world.clone(), flows, true);
+                flows.push((Flow::Next, world,));
+            }
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ScriptParser;
+    use crate::runner::{ ScriptRunner, ScriptedChoices };
+    use crate::world::cell::U32Cell;
+
+    /// Builds the control-flow graph of a script.
+    fn build(src : &[u8]) -> ControlFlowGraph {
+        let script = ScriptParser::parse(src.iter().copied().map(Ok)).unwrap();
+        ControlFlowGraph::build::<U32Cell>(&script, ArithMode::Checked)
+    }
+
+    #[test]
+    fn resolve_jumps() {
+        // Jumps forward 2, over the instruction at 4.
+        let cfg = build(b">- >- +- : >- >-");
+        assert_eq!(*cfg.successors(3), BTreeSet::from([Target::Ins(5)]));
+        assert_eq!(*cfg.successors(5), BTreeSet::from([Target::End]));
+        assert_eq!(cfg.diagnostics(), [CfgDiagnostic::Unreachable { start : 4, end : 5 }]);
+        // Jumps backward 1, from the first instruction.
+        let cfg = build(b":! >-");
+        assert_eq!(cfg.diagnostics(), [
+            CfgDiagnostic::JumpOutOfScript { index : 0, target : -1 },
+            CfgDiagnostic::Unreachable { start : 1, end : 2 }
+        ]);
+        // Only the taken side of a known condition is followed.
+        let cfg = build(b">- >- +-! :? >- >-");
+        assert_eq!(*cfg.successors(3), BTreeSet::from([Target::Ins(4)]));
+        assert!(cfg.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn unresolved_jumps() {
+        // Swaps input into the cell right of the origin.
+        let cfg = build(b">- ~- >- : >-");
+        assert_eq!(*cfg.successors(3), BTreeSet::from([Target::Unknown]));
+        assert_eq!(cfg.diagnostics(), [CfgDiagnostic::UnresolvedJump { index : 3 }]);
+        assert!((0..5).all(|index| cfg.is_reachable(index)));
+        // A random choice between two distances is resolved to both.
+        let cfg = build(b">- >- +- :# >- >- >-");
+        assert_eq!(*cfg.successors(3), BTreeSet::from([Target::Ins(4), Target::Ins(6)]));
+    }
+
+    #[test]
+    fn nested_jumps() {
+        // Jumps forward 2, then moves on by one, as the jump is inside a condition or random choice.
+        for (src, first,) in [(b">- >- +- :? >- >- >- >-", true,), (b">- >- +- :# >- >- >- >-", true,), (b">- >- +- :# >- >- >- >-", false,)] {
+            let cfg = build(src);
+            let script = ScriptParser::parse(src.iter().copied().map(Ok)).unwrap();
+            let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(script, "");
+            runner.set_choices(ScriptedChoices::new([first]));
+            runner.run_for(4).unwrap();
+            assert!(cfg.successors(3).contains(&Target::Ins(runner.script_head())));
+        }
+        let cfg = build(b">- >- +- :? >- >- >- >-");
+        assert_eq!(*cfg.successors(3), BTreeSet::from([Target::Ins(6)]));
+        assert_eq!(cfg.diagnostics(), [CfgDiagnostic::Unreachable { start : 4, end : 6 }]);
+    }
+
+    #[test]
+    fn loops() {
+        let src = include_bytes!("../../samples/echo_forever.isolang");
+        let cfg = build(src);
+        assert!(cfg.diagnostics().iter().all(CfgDiagnostic::is_note));
+        let cfg = build(include_bytes!("../../samples/simple_hello_world.isolang"));
+        assert!(cfg.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn head_out_of_range() {
+        let mut world = ConstWorld::<U32Cell>::new();
+        assert!(world.move_by(Coord::R.checked_mul(Coord::MAX_DISTANCE)));
+        assert!(! world.move_by(Some(Coord::R)));
+        assert!(! world.move_by(Coord::DL.checked_mul(isize::MIN)));
+        world.head = None;
+        assert!(world.move_by(Coord::R.checked_mul(isize::MAX)));
+        assert!(! world.move_by(None));
+    }
+
+}
@@ -0,0 +1,139 @@
+//! Worklist solver which follows an abstract world through a script until nothing more is learned.
+
+
+use crate::analysis::Target;
+use crate::runner::ins::Ins;
+use std::collections::{ BTreeMap, BTreeSet };
+
+
+/// What is known about a world at some point in a script.
+pub(super) trait AbstractWorld : Clone + PartialEq {
+
+    /// Creates a world like this one, about which nothing is known.
+    fn forget(&self) -> Self;
+
+    /// Forgets anything which differs from `other`, so that this describes both worlds.
+    ///
+    /// ### Returns
+    /// Returns `true` if anything was forgotten.
+    fn join(&mut self, other : &Self) -> bool;
+
+}
+
+
+/// Runs instructions on an [`AbstractWorld`].
+pub(super) trait AbstractExec<W : AbstractWorld> {
+
+    /// Runs the instruction at `index`, adding every path through it to `flows`.
+    ///
+    /// Paths which certainly fail are not added.
+    fn exec(&mut self, index : usize, ins : &Ins, world : W, flows : &mut Vec<(Flow, W,)>);
+
+}
+
+
+/// Where control goes after an instruction, on one path through it.
+pub(super) enum Flow {
+    /// To the next instruction.
+    Next,
+    /// To where a jump lands, which may be outside of the script.
+    Jump {
+        /// The index the jump lands on.
+        target : isize,
+        /// Whether the script head then moves on by one, as after a jump inside a condition or random choice.
+        nested : bool
+    },
+    /// To an unknown instruction.
+    Unresolved
+}
+
+
+/// Everything known about a script once the solver has finished.
+pub(super) struct Fixpoint<W : AbstractWorld> {
+
+    /// What is known about the world before each instruction, or `None` if it never runs.
+    pub(super) states     : Vec<Option<W>>,
+
+    /// Where control can go after each instruction.
+    pub(super) successors : Vec<BTreeSet<Target>>,
+
+    /// Where each resolved jump can land, by the index of the instruction containing it.
+    pub(super) jumps      : BTreeMap<usize, BTreeSet<isize>>,
+
+    /// The indices of the instructions containing jumps which could not be resolved.
+    pub(super) unresolved : BTreeSet<usize>
+
+}
+
+
+/// The number of times the state before an instruction is widened,
+/// before everything about it is forgotten so that the solver finishes.
+const MAX_VISITS : usize = 64;
+
+/// Follows `initial` through a script, starting at the first instruction.
+///
+/// *Note: An unresolved jump may land anywhere, so once any is reached,
+/// every instruction is reached with nothing known.*
+pub(super) fn solve<W : AbstractWorld>(script : &[Ins], exec : &mut impl AbstractExec<W>, initial : W) -> Fixpoint<W> {
+    let mut fixpoint = Fixpoint {
+        states     : vec![None; script.len()],
+        successors : vec![BTreeSet::new(); script.len()],
+        jumps      : BTreeMap::new(),
+        unresolved : BTreeSet::new()
+    };
+    let unknown      = initial.forget();
+    let mut visits   = vec![0; script.len()];
+    let mut worklist = BTreeSet::new();
+    if (! script.is_empty()) {
+        fixpoint.states[0] = Some(initial);
+        worklist.insert(0);
+    }
+
+    while let Some(index) = worklist.pop_first() {
+        let Some(world) = fixpoint.states[index].clone()
+            else { continue; };
+        let mut flows = Vec::new();
+        exec.exec(index, &script[index], world, &mut flows);
+        for (flow, world,) in flows {
+            let target = match (flow) {
+                Flow::Next         => index + 1,
+                Flow::Jump { target, nested } => {
+                    fixpoint.jumps.entry(index).or_default().insert(target);
+                    (target.max(0) as usize).saturating_add(usize::from(nested))
+                },
+                Flow::Unresolved   => {
+                    fixpoint.successors[index].insert(Target::Unknown);
+                    if (fixpoint.unresolved.insert(index)) {
+                        for (target, state,) in fixpoint.states.iter_mut().enumerate() {
+                            *state = Some(unknown.clone());
+                            worklist.insert(target);
+                        }
+                    }
+                    continue;
+                }
+            };
+            if (target >= script.len()) {
+                fixpoint.successors[index].insert(Target::End);
+                continue;
+            }
+            fixpoint.successors[index].insert(Target::Ins(target));
+            let changed = match (&mut fixpoint.states[target]) {
+                Some(state) => {
+                    visits[target] += 1;
+                    if (visits[target] > MAX_VISITS) {
+                        let changed = *state != unknown;
+                        *state = unknown.clone();
+                        changed
+                    } else { state.join(&world) }
+                },
+                state @ None => {
+                    *state = Some(world);
+                    true
+                }
+            };
+            if (changed) { worklist.insert(target); }
+        }
+    }
+
+    fixpoint
+}
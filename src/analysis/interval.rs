@@ -0,0 +1,521 @@
+//! Ranges of the values cells can hold, found by abstract interpretation.
+
+
+use crate::analysis::fixpoint::{ self, AbstractWorld, AbstractExec, Flow };
+use crate::runner::ins::Ins;
+use crate::world::{ Cell, Adj, Coord, Dir, ArithOp, ArithMode };
+use std::collections::BTreeMap;
+use core::fmt;
+
+
+/// An inclusive range of cell values.
+///
+/// [`i128::MIN`] and [`i128::MAX`] also stand for values too large to fit, for unbounded cells.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Interval {
+
+    /// The smallest value.
+    pub min : i128,
+
+    /// The largest value.
+    pub max : i128
+
+}
+
+impl Interval {
+
+    /// Creates a range holding a single value.
+    pub const fn exact(value : i128) -> Self { Self { min : value, max : value } }
+
+    /// Creates the range of every value a cell of type `C` can hold.
+    pub fn of<C : Cell>() -> Self {
+        let (min, max,) = C::BOUNDS.unwrap_or((i128::MIN, i128::MAX,));
+        Self { min, max }
+    }
+
+    /// Get the only value in this range, if it holds a single value.
+    pub fn as_exact(&self) -> Option<i128> { (self.min == self.max).then_some(self.min) }
+
+    /// Returns `true` if `value` is in this range.
+    pub fn contains(&self, value : i128) -> bool { self.min <= value && value <= self.max }
+
+    /// Gets the smallest range holding both this range and `other`.
+    fn hull(self, other : Self) -> Self {
+        Self { min : self.min.min(other.min), max : self.max.max(other.max) }
+    }
+
+    /// Gets the smallest range holding every value in `values`.
+    fn spanning(values : impl IntoIterator<Item = i128>) -> Self {
+        values.into_iter().fold(Self { min : i128::MAX, max : i128::MIN }, |range, value| range.hull(Self::exact(value)))
+    }
+
+    /// Gets the range of the quotients of this range divided by `rhs`, rounding towards zero.
+    ///
+    /// ### Returns
+    /// Returns `None` if `rhs` only holds zero.
+    fn div(self, rhs : Self) -> Option<Self> {
+        let neg = (rhs.min <= -1).then(|| Self { min : rhs.min, max : rhs.max.min(-1) });
+        let pos = (rhs.max >=  1).then(|| Self { min : rhs.min.max(1), max : rhs.max });
+        [neg, pos].into_iter().flatten()
+            .map(|rhs| Self::spanning([self.min, self.max].into_iter()
+                .flat_map(|l| [rhs.min, rhs.max].map(|r| l.checked_div(r).unwrap_or(i128::MAX)))
+            ))
+            .reduce(Self::hull)
+    }
+
+    /// Gets the range of the results of an arithmetic operation, before they are fitted into a cell.
+    ///
+    /// ### Returns
+    /// Returns `None` if the operation always divides by zero.
+    fn arith(self, op : ArithOp, rhs : Self) -> Option<Self> { Some(match (op) {
+        ArithOp::Add => Self { min : self.min.saturating_add(rhs.min), max : self.max.saturating_add(rhs.max) },
+        ArithOp::Sub => Self { min : self.min.saturating_sub(rhs.max), max : self.max.saturating_sub(rhs.min) },
+        ArithOp::Mul => Self::spanning([self.min, self.max].into_iter()
+            .flat_map(|l| [rhs.min, rhs.max].map(|r| l.saturating_mul(r)))
+        ),
+        ArithOp::Div => { return self.div(rhs); }
+    }) }
+
+    /// Fits the results of an arithmetic operation into a cell which holds `bounds`, following `mode`.
+    ///
+    /// ### Returns
+    /// Returns `None` if every result fails to fit under [`ArithMode::Checked`].
+    fn fit(self, bounds : Self, mode : ArithMode) -> Option<Self> { match (mode) {
+        ArithMode::Checked    => {
+            let fitted = Self { min : self.min.max(bounds.min), max : self.max.min(bounds.max) };
+            (fitted.min <= fitted.max).then_some(fitted)
+        },
+        ArithMode::Saturating => Some(Self {
+            min : self.min.clamp(bounds.min, bounds.max),
+            max : self.max.clamp(bounds.min, bounds.max)
+        }),
+        ArithMode::Wrapping   => Some(if (bounds.contains(self.min) && bounds.contains(self.max)) { self } else { bounds })
+    } }
+
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.as_exact()) {
+            Some(value) => write!(f, "{value}"),
+            None        => write!(f, "{}..={}", self.min, self.max)
+        }
+    }
+}
+
+
+/// Something found by a [`ValueAnalysis`], which will go wrong whenever the instruction runs.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ValueDiagnostic {
+
+    /// An [`Ins::SDiv`] always divides by zero.
+    DivisionByZero {
+        /// The index of the instruction.
+        index : usize
+    },
+
+    /// An [`Ins::Sub`] always gives a negative result, with unsigned cells.
+    Underflow {
+        /// The index of the instruction.
+        index : usize
+    },
+
+    /// An [`Ins::MoveHeadDynamic`] always moves by zero.
+    MoveByZero {
+        /// The index of the instruction.
+        index : usize
+    }
+
+}
+
+impl ValueDiagnostic {
+
+    /// Get the index of the instruction this is about.
+    pub fn index(&self) -> usize { match (self) {
+        Self::DivisionByZero { index } | Self::Underflow { index } | Self::MoveByZero { index } => *index
+    } }
+
+}
+
+impl fmt::Display for ValueDiagnostic {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::DivisionByZero { .. } => write!(f, "this always divides by zero"),
+        Self::Underflow      { .. } => write!(f, "this always subtracts a larger value from a smaller one, which underflows unsigned cells"),
+        Self::MoveByZero     { .. } => write!(f, "this always moves the head by zero")
+    } }
+}
+
+
+/// The ranges of the values cells can hold, before each instruction of a script.
+///
+/// Starts from the world a script runs on, where every cell holds one, and follows it through the script.
+/// Values read from input can be anything a cell can hold.
+pub struct ValueAnalysis {
+
+    /// The ranges before each instruction, or `None` if it never runs.
+    states      : Vec<Option<CellRanges>>,
+
+    /// What was found, in script order.
+    diagnostics : Vec<ValueDiagnostic>
+
+}
+
+impl ValueAnalysis {
+
+    /// Analyses a script, run with cells of type `C` under `arith_mode`.
+    pub fn analyze<C : Cell>(script : &[Ins], arith_mode : ArithMode) -> Self {
+        let mut exec = IntervalExec {
+            arith_mode,
+            unsigned    : Interval::of::<C>().min == 0,
+            diagnostics : None
+        };
+        let states = fixpoint::solve(script, &mut exec, CellRanges::new(Interval::of::<C>())).states;
+
+        let mut diagnostics = Vec::new();
+        exec.diagnostics = Some(&mut diagnostics);
+        for (index, (ins, state,),) in script.iter().zip(&states).enumerate() {
+            if let Some(state) = state {
+                exec.exec(index, ins, state.clone(), &mut Vec::new());
+            }
+        }
+        diagnostics.dedup();
+
+        Self { states, diagnostics }
+    }
+
+    /// Get the ranges of the cells before the instruction at `index` runs, or `None` if it never runs.
+    pub fn before(&self, index : usize) -> Option<&CellRanges> { self.states[index].as_ref() }
+
+    /// Get everything found, in script order.
+    pub fn diagnostics(&self) -> &[ValueDiagnostic] { &self.diagnostics }
+
+}
+
+
+/// The ranges of the values cells can hold, at some point in a script.
+#[derive(PartialEq, Clone, Debug)]
+pub struct CellRanges {
+
+    /// The position of the world head, if known.
+    head  : Option<Coord>,
+
+    /// Cells which differ from [`Self::rest`], by their position relative to the world head.
+    cells : BTreeMap<Coord, Interval>,
+
+    /// Every other cell.
+    rest  : Interval,
+
+    /// Every value a cell can hold.
+    full  : Interval
+
+}
+
+impl CellRanges {
+
+    /// Creates the ranges of the world a script starts on, with cells which can hold `full`.
+    fn new(full : Interval) -> Self { Self {
+        head  : Some(Coord::ZERO),
+        cells : BTreeMap::new(),
+        rest  : Interval::exact(1),
+        full
+    } }
+
+    /// Get the position of the world head, if known.
+    pub fn head(&self) -> Option<Coord> { self.head }
+
+    /// Get the range of values read from the cell at `offset` from the world head.
+    ///
+    /// *Note: If the position of the head is not known, the cell could be the origin,
+    /// and so could read anything from input.*
+    pub fn get(&self, offset : Coord) -> Interval {
+        let Some(head) = self.head
+            else { return self.full; };
+        if (head + offset == Coord::ZERO) {
+            if (head.half_side() == Some(Dir::R)) { self.full }
+            else { Interval::exact(1) }
+        } else {
+            *self.cells.get(&offset).unwrap_or(&self.rest)
+        }
+    }
+
+    /// Overwrites the cell at `offset` from the world head.
+    fn set(&mut self, offset : Coord, value : Interval) {
+        if (self.head.is_some_and(|head| head + offset == Coord::ZERO)) { return; }
+        if (value == self.rest) { self.cells.remove(&offset); }
+        else { self.cells.insert(offset, value); }
+    }
+
+    /// Moves the world head by a known offset, or `None` if the offset itself overflowed.
+    ///
+    /// ### Returns
+    /// Returns `false` if the head certainly leaves the range allowed by [`Coord::MAX_DISTANCE`].
+    fn move_by(&mut self, offset : Option<Coord>) -> bool {
+        // No two positions in range are more than twice the distance apart.
+        let Some(offset) = offset.filter(|offset| Coord::new(offset.r() / 2, offset.ul() / 2).in_range())
+            else { return false; };
+        if (offset == Coord::ZERO) { return true; }
+        if let Some(head) = self.head {
+            let Some(head) = head.checked_add(offset).filter(Coord::in_range)
+                else { return false; };
+            self.head = Some(head);
+        }
+        self.cells = self.cells.iter().map(|(cell, value,)| (*cell - offset, *value,)).collect();
+        true
+    }
+
+    /// Moves the world head by an unknown offset, so that any cell could be at any offset.
+    fn move_unknown(&mut self) {
+        self.head = None;
+        self.rest = self.cells.values().fold(self.rest, |rest, value| rest.hull(*value));
+        self.cells.clear();
+    }
+
+}
+
+impl AbstractWorld for CellRanges {
+
+    fn forget(&self) -> Self { Self {
+        head  : None,
+        cells : BTreeMap::new(),
+        rest  : self.full,
+        full  : self.full
+    } }
+
+    fn join(&mut self, other : &Self) -> bool {
+        let head = if (self.head == other.head) { self.head } else { None };
+        let rest = self.rest.hull(other.rest);
+        let mut cells = BTreeMap::new();
+        for offset in self.cells.keys().chain(other.cells.keys()) {
+            let value = self.cells.get(offset).unwrap_or(&self.rest)
+                .hull(*other.cells.get(offset).unwrap_or(&other.rest));
+            if (value != rest) { cells.insert(*offset, value); }
+        }
+        let joined = Self { head, cells, rest, full : self.full };
+        let changed = *self != joined;
+        *self = joined;
+        changed
+    }
+
+}
+
+
+/// Runs instructions on [`CellRanges`].
+struct IntervalExec<'l> {
+
+    /// What happens when the result of an arithmetic instruction does not fit in a cell.
+    arith_mode  : ArithMode,
+
+    /// Whether cells can not hold negative values.
+    unsigned    : bool,
+
+    /// Where anything found is reported, once the ranges are final.
+    diagnostics : Option<&'l mut Vec<ValueDiagnostic>>
+
+}
+
+impl AbstractExec<CellRanges> for IntervalExec<'_> {
+    fn exec(&mut self, index : usize, ins : &Ins, world : CellRanges, flows : &mut Vec<(Flow, CellRanges,)>) {
+        self.run(index, ins, world, flows, false);
+    }
+}
+
+impl IntervalExec<'_> {
+
+    /// The largest number of distances a jump is resolved to, before it is left unresolved.
+    const MAX_JUMP_TARGETS : u128 = 64;
+
+    /// Runs the instruction at `index`, adding every path through it to `flows`.
+    /// `nested` is set when `ins` is inside a condition or random choice.
+    fn run(&mut self, index : usize, ins : &Ins, mut world : CellRanges, flows : &mut Vec<(Flow, CellRanges,)>, nested : bool) {
+        match (ins) {
+
+            Ins::MoveHeadOne { adj, dir } => { if (! world.move_by(Some(Coord::from((*adj, *dir,))))) { return; } },
+
+            Ins::MoveHeadDynamic { adj, dir } => {
+                let distance = world.get(Coord::ZERO);
+                match (distance.as_exact()) {
+                    Some(0)        => { self.report(ValueDiagnostic::MoveByZero { index }); },
+                    Some(distance) => {
+                        if (! world.move_by(Coord::from((*adj, *dir,)).checked_mul(clamp_isize(distance)))) { return; }
+                    },
+                    None           => { world.move_unknown(); }
+                }
+            },
+
+            Ins::Add  { adj } => { if (! self.binop(index, &mut world, *adj, ArithOp::Add)) { return; } },
+            Ins::Sub  { adj } => { if (! self.binop(index, &mut world, *adj, ArithOp::Sub)) { return; } },
+            Ins::Mul  { adj } => { if (! self.binop(index, &mut world, *adj, ArithOp::Mul)) { return; } },
+            Ins::SDiv { adj } => { if (! self.binop(index, &mut world, *adj, ArithOp::Div)) { return; } },
+
+            Ins::Swap { adj } => {
+                let (l, r,) = Coord::ZERO + *adj;
+                let (lv, rv,) = (world.get(l), world.get(r),);
+                world.set(l, rv);
+                world.set(r, lv);
+            },
+
+            Ins::Noop | Ins::DumpWorld => { },
+
+            Ins::JumpThruCode { dir } => {
+                let distance = world.get(Coord::ZERO);
+                if (distance.max.abs_diff(distance.min) >= Self::MAX_JUMP_TARGETS) {
+                    flows.push((Flow::Unresolved, world,));
+                    return;
+                }
+                for distance in distance.min..=distance.max {
+                    let distance = match (dir) {
+                        Dir::L => clamp_isize(distance).saturating_neg(),
+                        Dir::R => clamp_isize(distance)
+                    };
+                    flows.push((Flow::Jump { target : (index as isize).saturating_add(distance), nested }, world.clone(),));
+                }
+                return;
+            },
+
+            Ins::IfNotZeroCond { ins } => { self.cond(index, ins, world, flows, false); return; },
+
+            Ins::IfZeroCond { ins } => { self.cond(index, ins, world, flows, true); return; },
+
+            Ins::RandomlyChoose { options } => {
+                self.run(index, &options.0, world.clone(), flows, true);
+                self.run(index, &options.1, world, flows, true);
+                return;
+            }
+
+        }
+        flows.push((Flow::Next, world,));
+    }
+
+    /// Reports something found, if the ranges are final.
+    fn report(&mut self, diagnostic : ValueDiagnostic) {
+        if let Some(diagnostics) = &mut self.diagnostics { diagnostics.push(diagnostic); }
+    }
+
+    /// Runs an arithmetic instruction.
+    ///
+    /// ### Returns
+    /// Returns `false` if the instruction always fails.
+    fn binop(&mut self, index : usize, world : &mut CellRanges, adj : Adj, op : ArithOp) -> bool {
+        let (l, r,) = Coord::ZERO + adj;
+        let (l, r,) = (world.get(l), world.get(r),);
+        let Some(value) = l.arith(op, r)
+            else {
+                self.report(ValueDiagnostic::DivisionByZero { index });
+                return false;
+            };
+        if (op == ArithOp::Sub && self.unsigned && value.max < 0) {
+            self.report(ValueDiagnostic::Underflow { index });
+        }
+        let Some(value) = value.fit(world.full, self.arith_mode)
+            else { return false; };
+        world.set(Coord::ZERO, value);
+        true
+    }
+
+    /// Runs a conditional instruction, which runs `ins` if the cell at the world head is zero
+    /// when `if_zero` is set, or is not zero otherwise.
+    fn cond(&mut self, index : usize, ins : &Ins, world : CellRanges, flows : &mut Vec<(Flow, CellRanges,)>, if_zero : bool) {
+        let value = world.get(Coord::ZERO);
+        // Learn from the condition, unless the head is not known. The cell could then be the origin,
+        // which reads a new value from input each time.
+        let reads_input = world.head.is_none();
+        let mut zero = (value.contains(0)).then(|| world.clone());
+        if let Some(zero) = &mut zero && ! reads_input {
+            zero.set(Coord::ZERO, Interval::exact(0));
+        }
+        let mut not_zero = (value != Interval::exact(0)).then_some(world);
+        if let Some(not_zero) = &mut not_zero && ! reads_input {
+            let min = if (value.min == 0) { 1 } else { value.min };
+            let max = if (value.max == 0) { -1 } else { value.max };
+            not_zero.set(Coord::ZERO, Interval { min, max });
+        }
+        let (run, skip,) = if (if_zero) { (zero, not_zero,) } else { (not_zero, zero,) };
+        if let Some(run) = run { self.run(index, ins, run, flows, true); }
+        if let Some(skip) = skip { flows.push((Flow::Next, skip,)); }
+    }
+
+}
+
+/// Converts a value to an [`isize`], clamping it as [`Cell::get_isize_val`] does.
+fn clamp_isize(value : i128) -> isize {
+    value.clamp(isize::MIN as i128, isize::MAX as i128) as isize
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ScriptParser;
+    use crate::runner::ScriptRunner;
+    use crate::world::cell::{ U8Cell, U32Cell, I32Cell };
+
+    /// Analyses a script.
+    fn analyze<C : Cell>(src : &[u8], arith_mode : ArithMode) -> ValueAnalysis {
+        let script = ScriptParser::parse(src.iter().copied().map(Ok)).unwrap();
+        ValueAnalysis::analyze::<C>(&script, arith_mode)
+    }
+
+    #[test]
+    fn interval_arith() {
+        let (a, b,) = (Interval { min : -3, max : 7 }, Interval { min : -2, max : 2 },);
+        assert_eq!(a.arith(ArithOp::Add, b), Some(Interval { min : -5, max : 9 }));
+        assert_eq!(a.arith(ArithOp::Sub, b), Some(Interval { min : -5, max : 9 }));
+        assert_eq!(a.arith(ArithOp::Mul, b), Some(Interval { min : -14, max : 14 }));
+        assert_eq!(a.arith(ArithOp::Div, b), Some(Interval { min : -7, max : 7 }));
+        assert_eq!(a.arith(ArithOp::Div, Interval::exact(0)), None);
+        let bounds = Interval::of::<U8Cell>();
+        assert_eq!(Interval { min : -5, max : 9 }.fit(bounds, ArithMode::Checked), Some(Interval { min : 0, max : 9 }));
+        assert_eq!(Interval { min : -5, max : -1 }.fit(bounds, ArithMode::Checked), None);
+        assert_eq!(Interval { min : -5, max : 9 }.fit(bounds, ArithMode::Wrapping), Some(bounds));
+        assert_eq!(Interval { min : 250, max : 300 }.fit(bounds, ArithMode::Saturating), Some(Interval { min : 250, max : 255 }));
+    }
+
+    #[test]
+    fn ranges() {
+        let analysis = analyze::<U32Cell>(b">- >- +- >- *- ~-", ArithMode::Checked);
+        let ranges = analysis.before(5).unwrap();
+        assert_eq!(ranges.get(Coord::ZERO), Interval::exact(2));
+        assert_eq!(ranges.get(Coord::L), Interval::exact(2));
+        assert_eq!(ranges.get(Coord::L + Coord::L), Interval::exact(1));
+        // Cells read from input can hold anything.
+        let analysis = analyze::<U8Cell>(b">- ~- >- +-", ArithMode::Checked);
+        assert_eq!(analysis.before(3).unwrap().get(Coord::ZERO), Interval::of::<U8Cell>());
+        assert_eq!(analysis.before(3).unwrap().get(Coord::L), Interval::exact(1));
+    }
+
+    #[test]
+    fn diagnostics() {
+        // Subtracts 1 from 1 for a zero, then divides by it.
+        let analysis = analyze::<I32Cell>(b">- >- >- +-! >-! *-! >-", ArithMode::Checked);
+        assert_eq!(analysis.diagnostics(), [ValueDiagnostic::DivisionByZero { index : 5 }]);
+        assert!(analysis.before(6).is_none());
+        let analysis = analyze::<U32Cell>(b">- >- +-! >- >- +-! >- +-!", ArithMode::Wrapping);
+        assert_eq!(analysis.diagnostics(), [ValueDiagnostic::Underflow { index : 7 }]);
+        assert!(analyze::<I32Cell>(b">- >- +-! >- >- +-! >- +-!", ArithMode::Checked).diagnostics().is_empty());
+        let analysis = analyze::<U32Cell>(b">- >- +-! ;-", ArithMode::Checked);
+        assert_eq!(analysis.diagnostics(), [ValueDiagnostic::MoveByZero { index : 3 }]);
+        // The division only runs when the cell is not zero.
+        let analysis = analyze::<I32Cell>(b">- >- +-! *-!?", ArithMode::Checked);
+        assert!(analysis.diagnostics().is_empty());
+        // The jump moves on past the division, which never runs.
+        let src = b">- >- >- +-! >-! :? *-! >-";
+        let analysis = analyze::<I32Cell>(src, ArithMode::Checked);
+        assert!(analysis.diagnostics().is_empty());
+        assert!(analysis.before(6).is_none());
+        let (mut runner, _,) = ScriptRunner::<I32Cell>::in_memory(ScriptParser::parse(src.iter().copied().map(Ok)).unwrap(), "");
+        assert!(runner.run().is_ok());
+        assert!(analyze::<U32Cell>(include_bytes!("../../samples/echo.isolang"), ArithMode::Checked).diagnostics().is_empty());
+    }
+
+    #[test]
+    fn head_out_of_range() {
+        let mut ranges = CellRanges::new(Interval::of::<U32Cell>());
+        assert!(ranges.move_by(Coord::R.checked_mul(Coord::MAX_DISTANCE)));
+        assert!(! ranges.move_by(Some(Coord::R)));
+        let mut ranges = ranges.forget();
+        assert!(ranges.move_by(Coord::L.checked_mul(Coord::MAX_DISTANCE * 2)));
+        assert!(! ranges.move_by(Coord::R.checked_mul(isize::MAX)));
+        assert!(! ranges.move_by(None));
+    }
+
+}
@@ -0,0 +1,10 @@
+//! Static analysis of scripts, without running them.
+
+
+mod fixpoint;
+
+mod cfg;
+pub use cfg::{ ControlFlowGraph, Target, CfgDiagnostic };
+
+mod interval;
+pub use interval::{ ValueAnalysis, CellRanges, Interval, ValueDiagnostic };
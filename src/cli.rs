@@ -2,11 +2,15 @@
 
 
 use std::path::PathBuf;
+use std::time::Duration;
 pub use clap::Parser;
-use clap::{Subcommand, ValueEnum };
+use clap::{ Args, Subcommand, ValueEnum };
+use isolang::parser::{ ParseOptions, UnknownChars };
+use isolang::world::{ ArithMode, EofPolicy, WorldFormat };
+use isolang::runner::{ TraceFormat, Engine };
 
 
-#[allow(missing_docs)]
+#[allow(missing_docs, clippy::missing_docs_in_private_items)]
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
@@ -15,34 +19,334 @@ pub struct Cli {
 }
 
 
-#[allow(missing_docs)]
+#[allow(missing_docs, clippy::missing_docs_in_private_items)]
 #[derive(Subcommand)]
 pub enum CliCommand {
 
     /// Run an Isolang script.
     Run {
 
-        /// The type of cells in the world.
-        #[clap(short = 'c', long, default_value = "u32")]
-        cell_mode    : CellMode,
+        #[clap(flatten)]
+        parse_args  : ParseArgs,
 
-        #[cfg(debug_assertions)]
-        /// The margin shown from the result of the `@` instruction.
-        #[clap(short = 'm', long, default_value_t = 1)]
-        world_margin : usize,
+        #[clap(flatten)]
+        run_args    : RunArgs,
 
         /// The source script file to run.
-        source_file  : PathBuf
+        source_file : PathBuf
+
+    },
+
+    /// Run an Isolang script in an interactive debugger.
+    Debug {
+
+        #[clap(flatten)]
+        parse_args  : ParseArgs,
+
+        #[clap(flatten)]
+        run_args    : RunArgs,
+
+        /// A file to use as the input of the script, as stdin is used for debugger commands.
+        #[clap(short = 'i', long)]
+        input       : Option<PathBuf>,
+
+        /// How many of the most recent steps are recorded, so that they can be undone.
+        #[clap(long, default_value_t = 100_000)]
+        history     : usize,
+
+        /// The source script file to debug.
+        source_file : PathBuf
+
+    },
+
+    /// Check an Isolang script for errors, without running it.
+    ///
+    /// Exits with failure if any warning is reported. Notes do not count.
+    Check {
+
+        #[clap(flatten)]
+        parse_args  : ParseArgs,
+
+        /// The type of cells the script is checked with.
+        #[clap(short = 'c', long, default_value = "u32")]
+        cell_mode   : CellMode,
+
+        /// What happens when the result of an arithmetic instruction does not fit in a cell.
+        #[clap(short = 'a', long, default_value = "checked")]
+        arith_mode  : ArithModeArg,
+
+        /// The source script file to check.
+        source_file : PathBuf
+
+    },
+
+    /// Rewrite an Isolang script into canonical layout.
+    Fmt {
+
+        #[clap(flatten)]
+        parse_args  : ParseArgs,
+
+        /// Don't write the file, but exit with an error if it is not formatted.
+        #[clap(long)]
+        check       : bool,
+
+        /// The source script file to format.
+        source_file : PathBuf
+
+    }
+
+}
+
+/// Options for running a script.
+#[derive(Args)]
+pub struct RunArgs {
+
+    /// The type of cells in the world.
+    #[clap(short = 'c', long, default_value = "u32")]
+    pub cell_mode     : CellMode,
+
+    /// What happens when the result of an arithmetic instruction does not fit in a cell.
+    #[clap(short = 'a', long, default_value = "checked")]
+    pub arith_mode    : ArithModeArg,
+
+    /// What happens when input is read after its end.
+    #[clap(short = 'e', long, default_value = "halt")]
+    pub eof           : EofPolicyArg,
+
+    /// How instructions are run.
+    #[clap(long, default_value = "tree")]
+    pub engine        : EngineArg,
+
+    /// Seed for the random choices made by `#`, picked at random if not given.
+    #[clap(long)]
+    pub seed          : Option<u64>,
+
+    /// Write a trace of every instruction run to stderr.
+    #[clap(long, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "text")]
+    pub trace         : Option<TraceFormatArg>,
+
+    /// Stop with an error after running this many instructions.
+    #[clap(long)]
+    pub max_steps     : Option<u64>,
+
+    /// Stop with an error after running for this many seconds.
+    #[clap(long, value_parser = parse_seconds)]
+    pub timeout       : Option<Duration>,
+
+    /// Stop with an error when the script returns to a state it was already in,
+    /// without any input, output or random choices since.
+    #[clap(long)]
+    pub detect_cycles : bool,
+
+    /// Where the `@` instruction dumps the world: `stdout`, `stderr`, `off`, or a file path.
+    #[clap(long, value_name = "TARGET", default_value = "stdout", value_parser = parse_dump_target)]
+    pub dump_world    : DumpTarget,
+
+    /// The margin shown from the result of the `@` instruction.
+    #[clap(short = 'm', long, default_value_t = 1)]
+    pub world_margin  : usize,
+
+    /// How the world is shown by the `@` instruction.
+    #[clap(long, default_value = "text")]
+    pub world_format  : WorldFormatArg,
+
+    /// Whether the world is shown with colours: `auto` uses colours only when writing to a terminal.
+    #[clap(long, default_value = "auto")]
+    pub color         : ColorArg,
+
+    /// Show cells as the characters they would write, where printable, instead of numbers.
+    #[clap(long)]
+    pub world_chars   : bool
+
+}
 
+/// How the world is shown.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum WorldFormatArg {
+    /// A grid of cells.
+    Text,
+    /// A JSON object holding the head, the origin and every non-default cell.
+    Json
+}
+
+/// Whether the world is shown with colours.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ColorArg {
+    /// Colours are used only when writing to a terminal.
+    Auto,
+    /// Colours are always used.
+    Always,
+    /// Colours are never used.
+    Never
+}
+
+impl RunArgs {
+
+    /// Gets how the world is shown, when written to a stream which is a terminal if `is_terminal`.
+    pub fn world_format(&self, is_terminal : bool) -> WorldFormat {
+        match (self.world_format, self.color,) {
+            (WorldFormatArg::Json, _,)                 => WorldFormat::Json,
+            (WorldFormatArg::Text, ColorArg::Always,)  => WorldFormat::Color,
+            (WorldFormatArg::Text, ColorArg::Never,)   => WorldFormat::Plain,
+            (WorldFormatArg::Text, ColorArg::Auto,)    => if (is_terminal) { WorldFormat::Color } else { WorldFormat::Plain }
+        }
     }
 
 }
 
+/// Where the `@` instruction dumps the world.
+#[derive(Clone)]
+pub enum DumpTarget {
+    /// The world is not dumped.
+    Off,
+    /// The world is dumped to stdout.
+    Stdout,
+    /// The world is dumped to stderr.
+    Stderr,
+    /// The world is dumped to a file, which is created or truncated.
+    File(PathBuf)
+}
+
+/// Parses where the `@` instruction dumps the world.
+fn parse_dump_target(arg : &str) -> Result<DumpTarget, String> {
+    Ok(match (arg) {
+        "off"    => DumpTarget::Off,
+        "stdout" => DumpTarget::Stdout,
+        "stderr" => DumpTarget::Stderr,
+        path     => DumpTarget::File(PathBuf::from(path))
+    })
+}
+
+/// Parses a (possibly fractional) number of seconds.
+fn parse_seconds(arg : &str) -> Result<Duration, String> {
+    let secs = arg.parse::<f64>().map_err(|err| err.to_string())?;
+    Duration::try_from_secs_f64(secs).map_err(|err| err.to_string())
+}
+
+
 /// The type of cells in the world.
-#[derive(ValueEnum, Clone)]
+#[derive(ValueEnum, Clone, Copy)]
 pub enum CellMode {
     /// 8-bit unsigned integers.
     U8,
     /// 32-bit unsigned integers.
-    U32
+    U32,
+    /// 8-bit signed integers.
+    I8,
+    /// 32-bit signed integers.
+    I32,
+    /// 64-bit signed integers.
+    I64,
+    /// Arbitrary-precision signed integers.
+    Big
+}
+
+
+/// What happens when the result of an arithmetic instruction does not fit in a cell.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ArithModeArg {
+    /// The result wraps around at the bounds of the cell.
+    Wrapping,
+    /// The result is clamped to the bounds of the cell.
+    Saturating,
+    /// The script stops with an error.
+    Checked
+}
+
+impl From<ArithModeArg> for ArithMode {
+    fn from(arg : ArithModeArg) -> Self { match (arg) {
+        ArithModeArg::Wrapping   => Self::Wrapping,
+        ArithModeArg::Saturating => Self::Saturating,
+        ArithModeArg::Checked    => Self::Checked
+    } }
+}
+
+
+/// What happens when input is read after its end.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum EofPolicyArg {
+    /// A cell containing zero is read.
+    Zero,
+    /// A cell containing -1 is read for signed cells, or the maximum value for unsigned cells.
+    Sentinel,
+    /// The script stops running, as if it had reached its end.
+    Halt,
+    /// The script stops with an error.
+    Error
+}
+
+impl From<EofPolicyArg> for EofPolicy {
+    fn from(arg : EofPolicyArg) -> Self { match (arg) {
+        EofPolicyArg::Zero     => Self::Zero,
+        EofPolicyArg::Sentinel => Self::Sentinel,
+        EofPolicyArg::Halt     => Self::Halt,
+        EofPolicyArg::Error    => Self::Error
+    } }
+}
+
+
+/// How each step of a trace is formatted.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum TraceFormatArg {
+    /// One human readable line per step.
+    Text,
+    /// One JSON object per line.
+    Json
+}
+
+impl From<TraceFormatArg> for TraceFormat {
+    fn from(arg : TraceFormatArg) -> Self { match (arg) {
+        TraceFormatArg::Text => Self::Text,
+        TraceFormatArg::Json => Self::JsonLines
+    } }
+}
+
+
+/// How instructions are run.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum EngineArg {
+    /// Walk the tree of instructions each step.
+    Tree,
+    /// Lower the script to flat bytecode first.
+    Bytecode,
+    /// Like `bytecode`, but also merge runs of head moves, each of which then runs as a single step.
+    /// Step counts, and so `--max-steps`, `--detect-cycles` and traces, then differ from `tree`.
+    Optimized
+}
+
+impl From<EngineArg> for Engine {
+    fn from(arg : EngineArg) -> Self { match (arg) {
+        EngineArg::Tree      => Self::Tree,
+        EngineArg::Bytecode  => Self::Bytecode,
+        EngineArg::Optimized => Self::Optimized
+    } }
+}
+
+
+/// Options for parsing a script.
+#[derive(Args)]
+pub struct ParseArgs {
+
+    /// How characters which are not part of any instruction or comment are handled.
+    #[clap(short = 'u', long, default_value = "reject")]
+    unknown_chars : UnknownCharsMode
+
+}
+
+impl From<&ParseArgs> for ParseOptions {
+    fn from(args : &ParseArgs) -> Self { Self {
+        unknown_chars : match (args.unknown_chars) {
+            UnknownCharsMode::Reject => UnknownChars::Reject,
+            UnknownCharsMode::Ignore => UnknownChars::Ignore
+        }
+    } }
+}
+
+/// How characters which are not part of any instruction or comment are handled.
+#[derive(ValueEnum, Clone, Copy)]
+pub enum UnknownCharsMode {
+    /// Unknown characters are errors.
+    Reject,
+    /// Unknown characters are skipped as prose, with a warning for lines which also contain instructions.
+    Ignore
 }
@@ -0,0 +1,198 @@
+//! Commands accepted by the debugger REPL.
+
+
+use crate::world::Coord;
+use core::fmt;
+use std::error::Error;
+
+
+/// A command entered into the debugger REPL.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Command {
+
+    /// Run a number of steps.
+    Step {
+        /// The number of steps to run.
+        steps : u64
+    },
+
+    /// Run until a breakpoint or watchpoint is hit, or the script finishes.
+    Continue,
+
+    /// Undo a number of steps.
+    Back {
+        /// The number of steps to undo.
+        steps : u64
+    },
+
+    /// Undo steps until the last write to a cell has been undone.
+    LastWrite(Coord),
+
+    /// Add a breakpoint.
+    Break(Breakpoint),
+
+    /// Remove a breakpoint.
+    Delete(Breakpoint),
+
+    /// Watch a cell for changes.
+    Watch(Coord),
+
+    /// Stop watching a cell.
+    Unwatch(Coord),
+
+    /// Print the world around the head.
+    PrintWorld,
+
+    /// Print the value of a single cell.
+    PrintCell(Coord),
+
+    /// Print the current position and all breakpoints and watchpoints.
+    Info,
+
+    /// Print the list of commands.
+    Help,
+
+    /// Exit the debugger.
+    Quit
+
+}
+
+/// A place at which running stops.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Breakpoint {
+
+    /// Stops before the instruction at this index is run.
+    Ins(usize),
+
+    /// Stops when the world head moves onto this position.
+    Head(Coord)
+
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::Ins(index)  => write!(f, "instruction {index}"),
+        Self::Head(coord) => write!(f, "world head at {coord}")
+    } }
+}
+
+
+impl Command {
+
+    /// The help text listing every command.
+    pub const HELP : &'static str = "\
+s, step [N]        run N steps (default 1)
+c, continue        run until a breakpoint or watchpoint is hit
+bk, back [N]       undo N steps (default 1)
+lw, lastwrite R,UL undo steps until the last write to the cell at R,UL is undone
+b, break INDEX     stop before the instruction at INDEX
+b, break R,UL      stop when the world head moves onto R,UL
+d, delete INDEX    remove a breakpoint on an instruction
+d, delete R,UL     remove a breakpoint on a world head position
+w, watch R,UL      stop when the cell at R,UL changes
+u, unwatch R,UL    stop watching the cell at R,UL
+p, print           print the world around the head
+p, print R,UL      print the value of the cell at R,UL
+i, info            print the position, breakpoints and watchpoints
+h, help            print this help
+q, quit            exit the debugger";
+
+    /// The names of every command, including short forms.
+    const NAMES : &'static [&'static str] = &[
+        "s", "step", "c", "continue", "bk", "back", "lw", "lastwrite", "b", "break", "d", "delete", "w", "watch",
+        "u", "unwatch", "p", "print", "i", "info", "h", "help", "q", "quit"
+    ];
+
+    /// Parses a single line of input.
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Ok(Some(_))` if a command was parsed.
+    /// - `Ok(None)` if the line was blank.
+    /// - `Err(_)` if the line was not a valid command.
+    pub fn parse(line : &str) -> Result<Option<Self>, CommandError> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next()
+            else { return Ok(None); };
+        let arg = words.collect::<Vec<_>>().join(" ");
+        let arg = (! arg.is_empty()).then_some(arg.as_str());
+        let command = match (name, arg,) {
+            ("s" | "step", None,)           => Self::Step { steps : 1 },
+            ("s" | "step", Some(steps),)    => Self::Step { steps : steps.parse().map_err(|_| CommandError::BadArg)? },
+            ("c" | "continue", None,)       => Self::Continue,
+            ("bk" | "back", None,)          => Self::Back { steps : 1 },
+            ("bk" | "back", Some(steps),)   => Self::Back { steps : steps.parse().map_err(|_| CommandError::BadArg)? },
+            ("lw" | "lastwrite", Some(arg),) => Self::LastWrite(parse_coord(arg)?),
+            ("b" | "break", Some(arg),)     => Self::Break(parse_breakpoint(arg)?),
+            ("d" | "delete", Some(arg),)    => Self::Delete(parse_breakpoint(arg)?),
+            ("w" | "watch", Some(arg),)     => Self::Watch(parse_coord(arg)?),
+            ("u" | "unwatch", Some(arg),)   => Self::Unwatch(parse_coord(arg)?),
+            ("p" | "print", None,)          => Self::PrintWorld,
+            ("p" | "print", Some(arg),)     => Self::PrintCell(parse_coord(arg)?),
+            ("i" | "info", None,)           => Self::Info,
+            ("h" | "help", None,)           => Self::Help,
+            ("q" | "quit", None,)           => Self::Quit,
+            _ if (Self::NAMES.contains(&name)) => { return Err(CommandError::BadArg); },
+            _ => { return Err(CommandError::Unknown(name.to_string())); }
+        };
+        Ok(Some(command))
+    }
+
+}
+
+/// Parses a breakpoint, as either an instruction index or a coordinate.
+fn parse_breakpoint(arg : &str) -> Result<Breakpoint, CommandError> {
+    if let Ok(index) = arg.parse() {
+        Ok(Breakpoint::Ins(index))
+    } else {
+        Ok(Breakpoint::Head(parse_coord(arg)?))
+    }
+}
+
+/// Parses a coordinate argument.
+fn parse_coord(arg : &str) -> Result<Coord, CommandError> {
+    arg.parse().map_err(|_| CommandError::BadArg)
+}
+
+
+/// A line of input was not a valid command.
+#[derive(Debug)]
+pub enum CommandError {
+    /// The command name is not known.
+    Unknown(String),
+    /// The arguments given to the command were missing or invalid.
+    BadArg
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::Unknown(name) => write!(f, "unknown command `{name}`, try `help`"),
+        Self::BadArg        => write!(f, "bad arguments, try `help`")
+    } }
+}
+
+impl Error for CommandError { }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(Command::parse("  ").unwrap(), None);
+        assert_eq!(Command::parse("s").unwrap(), Some(Command::Step { steps : 1 }));
+        assert_eq!(Command::parse("step 20").unwrap(), Some(Command::Step { steps : 20 }));
+        assert_eq!(Command::parse("back 3").unwrap(), Some(Command::Back { steps : 3 }));
+        assert_eq!(Command::parse("lw 1,1").unwrap(), Some(Command::LastWrite(Coord::new(1, 1))));
+        assert_eq!(Command::parse("b 4").unwrap(), Some(Command::Break(Breakpoint::Ins(4))));
+        assert_eq!(Command::parse("break 1, -2").unwrap(), Some(Command::Break(Breakpoint::Head(Coord::new(1, -2)))));
+        assert_eq!(Command::parse("w (r:0,ul:3)").unwrap(), Some(Command::Watch(Coord::new(0, 3))));
+        assert_eq!(Command::parse("p").unwrap(), Some(Command::PrintWorld));
+        assert_eq!(Command::parse("p 2,2").unwrap(), Some(Command::PrintCell(Coord::new(2, 2))));
+        assert!(matches!(Command::parse("step x"), Err(CommandError::BadArg)));
+        assert!(matches!(Command::parse("watch"), Err(CommandError::BadArg)));
+        assert!(matches!(Command::parse("jump 3"), Err(CommandError::Unknown(_))));
+    }
+
+}
@@ -0,0 +1,382 @@
+//! Interactive Isolang debugger.
+
+
+use crate::world::{ Cell, Coord };
+use crate::world::cell::CellValue;
+use crate::runner::{ ScriptRunner, RuntimeError };
+use crate::printer::ScriptPrinter;
+use std::collections::{ BTreeMap, BTreeSet };
+use std::io::{ self, BufRead, Write };
+
+
+mod command;
+pub use command::{ Command, CommandError, Breakpoint };
+
+
+/// Isolang debugger, which runs a [`ScriptRunner`] until it hits a breakpoint or watchpoint.
+pub struct Debugger<C : Cell> {
+
+    /// The runner being debugged.
+    runner      : ScriptRunner<C>,
+
+    /// The places at which running stops.
+    breakpoints : BTreeSet<Breakpoint>,
+
+    /// The watched cells, and the last value seen in each.
+    watchpoints : BTreeMap<Coord, C>
+
+}
+
+/// Why the debugger stopped running the script.
+#[derive(PartialEq, Debug)]
+pub enum Stop<C : Cell> {
+
+    /// The requested number of steps were run.
+    Stepped,
+
+    /// The script has finished.
+    Finished,
+
+    /// There are no more recorded steps to undo.
+    HistoryStart,
+
+    /// A breakpoint was reached.
+    Breakpoint(Breakpoint),
+
+    /// Some watched cells changed, in order of position.
+    Watchpoint(Vec<WatchChange<C>>)
+
+}
+
+/// A change to a watched cell, made by a single step.
+#[derive(PartialEq, Debug)]
+pub struct WatchChange<C : Cell> {
+
+    /// The position of the watched cell.
+    pub coord : Coord,
+
+    /// The value of the cell before the step.
+    pub old   : C,
+
+    /// The value of the cell after the step.
+    pub new   : C
+
+}
+
+impl<C : Cell> Debugger<C> {
+
+    /// Construct a new debugger, with no breakpoints or watchpoints.
+    pub fn new(runner : ScriptRunner<C>) -> Self { Self {
+        runner,
+        breakpoints : BTreeSet::new(),
+        watchpoints : BTreeMap::new()
+    } }
+
+    /// Get a non-mutable reference to the [`ScriptRunner`] being debugged.
+    pub fn runner(&self) -> &ScriptRunner<C> { &self.runner }
+
+    /// Get a mutable reference to the [`ScriptRunner`] being debugged.
+    pub fn runner_mut(&mut self) -> &mut ScriptRunner<C> { &mut self.runner }
+
+    /// Adds a breakpoint.
+    ///
+    /// ### Returns
+    /// Returns `false` if the breakpoint was already set.
+    pub fn add_breakpoint(&mut self, breakpoint : Breakpoint) -> bool { self.breakpoints.insert(breakpoint) }
+
+    /// Removes a breakpoint.
+    ///
+    /// ### Returns
+    /// Returns `false` if the breakpoint was not set.
+    pub fn remove_breakpoint(&mut self, breakpoint : Breakpoint) -> bool { self.breakpoints.remove(&breakpoint) }
+
+    /// Watches a cell, stopping whenever its value changes.
+    ///
+    /// ### Returns
+    /// Returns `false` if the cell was already watched.
+    pub fn add_watchpoint(&mut self, coord : Coord) -> bool {
+        let value = self.runner.world().peek(coord);
+        self.watchpoints.insert(coord, value).is_none()
+    }
+
+    /// Stops watching a cell.
+    ///
+    /// ### Returns
+    /// Returns `false` if the cell was not watched.
+    pub fn remove_watchpoint(&mut self, coord : Coord) -> bool { self.watchpoints.remove(&coord).is_some() }
+
+}
+
+impl<C : Cell> Debugger<C> {
+
+    /// Runs at most `steps` steps, stopping early at any breakpoint or watchpoint.
+    pub fn step(&mut self, steps : u64) -> Result<Stop<C>, RuntimeError> {
+        for _ in 0..steps {
+            if let Some(stop) = self.step_once()? { return Ok(stop); }
+        }
+        Ok(Stop::Stepped)
+    }
+
+    /// Runs until a breakpoint or watchpoint is hit, or the script finishes.
+    pub fn resume(&mut self) -> Result<Stop<C>, RuntimeError> {
+        loop {
+            if let Some(stop) = self.step_once()? { return Ok(stop); }
+        }
+    }
+
+    /// Undoes at most `steps` steps.
+    ///
+    /// *Note: Breakpoints and watchpoints are not checked while stepping back.*
+    pub fn step_back(&mut self, steps : u64) -> Stop<C> {
+        let mut stop = Stop::Stepped;
+        for _ in 0..steps {
+            if (! self.runner.step_back()) {
+                stop = Stop::HistoryStart;
+                break;
+            }
+        }
+        self.refresh_watchpoints();
+        stop
+    }
+
+    /// Undoes steps until the last write to `coord` has been undone, so that it is the next step to run.
+    pub fn back_to_write(&mut self, coord : Coord) -> Stop<C> {
+        let found = self.runner.step_back_to_write(coord);
+        self.refresh_watchpoints();
+        if (found) { Stop::Stepped } else { Stop::HistoryStart }
+    }
+
+    /// Updates the last seen value of each watched cell, so that undone changes are not reported.
+    fn refresh_watchpoints(&mut self) {
+        for (coord, last,) in &mut self.watchpoints {
+            *last = self.runner.world().peek(*coord);
+        }
+    }
+
+    /// Runs a single step.
+    ///
+    /// ### Returns
+    /// Returns `Ok(Some(_))` if running should stop after this step.
+    fn step_once(&mut self) -> Result<Option<Stop<C>>, RuntimeError> {
+        let head = self.runner.world().head();
+        if (! self.runner.run_next()?) { return Ok(Some(Stop::Finished)); }
+        let mut changes = Vec::new();
+        for (coord, last,) in &mut self.watchpoints {
+            let value = self.runner.world().peek(*coord);
+            if (value != *last) {
+                let old = core::mem::replace(last, value.clone());
+                changes.push(WatchChange { coord : *coord, old, new : value });
+            }
+        }
+        if (! changes.is_empty()) { return Ok(Some(Stop::Watchpoint(changes))); }
+        let breakpoint = Breakpoint::Ins(self.runner.script_head());
+        if (self.breakpoints.contains(&breakpoint)) { return Ok(Some(Stop::Breakpoint(breakpoint))); }
+        // Only stops when the head moves onto the position, so that running can continue past it.
+        let breakpoint = Breakpoint::Head(self.runner.world().head());
+        if (self.runner.world().head() != head && self.breakpoints.contains(&breakpoint)) { return Ok(Some(Stop::Breakpoint(breakpoint))); }
+        Ok(None)
+    }
+
+}
+
+impl<C : Cell> Debugger<C> {
+
+    /// The number of cells shown on each side of the head when the world is printed.
+    const PRINT_RADIUS : usize = 4;
+
+    /// Runs an interactive REPL, reading commands from `input` until it ends or `quit` is entered.
+    pub fn repl(&mut self, mut input : impl BufRead, mut output : impl Write) -> io::Result<()> {
+        self.write_position(&mut output)?;
+        let mut line = String::new();
+        loop {
+            write!(output, "(isolang) ")?;
+            output.flush()?;
+            line.clear();
+            if (input.read_line(&mut line)? == 0) {
+                writeln!(output)?;
+                return Ok(());
+            }
+            let command = match (Command::parse(&line)) {
+                Ok(Some(command)) => command,
+                Ok(None)          => { continue; },
+                Err(err)          => {
+                    writeln!(output, "error: {err}")?;
+                    continue;
+                }
+            };
+            match (command) {
+                Command::Step { steps } => {
+                    let stop = self.step(steps);
+                    self.write_stop(&mut output, stop)?;
+                },
+                Command::Continue => {
+                    let stop = self.resume();
+                    self.write_stop(&mut output, stop)?;
+                },
+                Command::Back { steps } => {
+                    let stop = self.step_back(steps);
+                    self.write_stop(&mut output, Ok(stop))?;
+                },
+                Command::LastWrite(coord) => {
+                    let stop = self.back_to_write(coord);
+                    self.write_stop(&mut output, Ok(stop))?;
+                },
+                Command::Break(breakpoint) => {
+                    if (! self.add_breakpoint(breakpoint)) { writeln!(output, "breakpoint on {breakpoint} already set")?; }
+                },
+                Command::Delete(breakpoint) => {
+                    if (! self.remove_breakpoint(breakpoint)) { writeln!(output, "no breakpoint on {breakpoint}")?; }
+                },
+                Command::Watch(coord) => {
+                    if (! self.add_watchpoint(coord)) { writeln!(output, "cell {coord} already watched")?; }
+                },
+                Command::Unwatch(coord) => {
+                    if (! self.remove_watchpoint(coord)) { writeln!(output, "cell {coord} not watched")?; }
+                },
+                Command::PrintWorld => { writeln!(output, "{}", self.runner.world().around_head(Self::PRINT_RADIUS))?; },
+                Command::PrintCell(coord) => {
+                    writeln!(output, "{coord} = {}", CellValue(&self.runner.world().peek(coord)))?;
+                },
+                Command::Info => {
+                    self.write_position(&mut output)?;
+                    for breakpoint in &self.breakpoints {
+                        writeln!(output, "breakpoint on {breakpoint}")?;
+                    }
+                    for (coord, value,) in &self.watchpoints {
+                        writeln!(output, "watching {coord} = {}", CellValue(value))?;
+                    }
+                },
+                Command::Help => { writeln!(output, "{}", Command::HELP)?; },
+                Command::Quit => { return Ok(()); }
+            }
+        }
+    }
+
+    /// Writes why running stopped, followed by the current position.
+    fn write_stop(&self, output : &mut impl Write, stop : Result<Stop<C>, RuntimeError>) -> io::Result<()> {
+        writeln!(output)?;
+        match (stop) {
+            Ok(Stop::Stepped)                         => { },
+            Ok(Stop::Finished)                        => { writeln!(output, "script finished")?; },
+            Ok(Stop::HistoryStart)                    => { writeln!(output, "reached the start of the recorded history")?; },
+            Ok(Stop::Breakpoint(breakpoint))          => { writeln!(output, "hit breakpoint on {breakpoint}")?; },
+            Ok(Stop::Watchpoint(changes))             => {
+                for WatchChange { coord, old, new } in changes {
+                    writeln!(output, "cell {coord} changed from {} to {}", CellValue(&old), CellValue(&new))?;
+                }
+            },
+            Err(err) => { writeln!(output, "error: {err}")?; }
+        }
+        self.write_position(output)
+    }
+
+    /// Writes the step count, next instruction and world head.
+    fn write_position(&self, output : &mut impl Write) -> io::Result<()> {
+        let script_head = self.runner.script_head();
+        let ins = match (self.runner.script().get(script_head)) {
+            Some(ins) => ScriptPrinter::describe_ins(ins),
+            None      => "<end>".to_string()
+        };
+        writeln!(output, "step {}, next [{script_head}] {ins}, head {}", self.runner.steps(), self.runner.world().head())
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ScriptParser;
+    use crate::world::cell::U32Cell;
+
+    /// Creates a debugger for a script, running in memory with no input.
+    fn debugger(src : &[u8]) -> Debugger<U32Cell> {
+        let script = ScriptParser::parse(src.iter().copied().map(Ok)).unwrap();
+        Debugger::new(ScriptRunner::in_memory(script, "").0)
+    }
+
+    #[test]
+    fn breakpoints() {
+        let mut debugger = debugger(b">- >- >- >-");
+        assert!(debugger.add_breakpoint(Breakpoint::Ins(3)));
+        assert!(debugger.add_breakpoint(Breakpoint::Head(Coord::new(2, 0))));
+        assert!(! debugger.add_breakpoint(Breakpoint::Ins(3)));
+        assert_eq!(debugger.step(1).unwrap(), Stop::Stepped);
+        assert_eq!(debugger.resume().unwrap(), Stop::Breakpoint(Breakpoint::Head(Coord::new(2, 0))));
+        assert_eq!(debugger.resume().unwrap(), Stop::Breakpoint(Breakpoint::Ins(3)));
+        assert_eq!(debugger.step(5).unwrap(), Stop::Finished);
+        assert_eq!(debugger.runner().steps(), 4);
+    }
+
+    #[test]
+    fn head_breakpoints() {
+        // Staying on the position does not stop again.
+        let mut debugger = debugger(b">- >- +- +- +-");
+        assert!(debugger.add_breakpoint(Breakpoint::Head(Coord::new(2, 0))));
+        assert_eq!(debugger.resume().unwrap(), Stop::Breakpoint(Breakpoint::Head(Coord::new(2, 0))));
+        assert_eq!(debugger.resume().unwrap(), Stop::Finished);
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut debugger = debugger(b">- >- +- >- +-");
+        let cell = Coord::new(2, 0);
+        assert!(debugger.add_watchpoint(cell));
+        assert_eq!(debugger.resume().unwrap(), Stop::Watchpoint(vec![WatchChange { coord : cell, old : U32Cell::ONE, new : U32Cell::from('\u{2}') }]));
+        assert_eq!(debugger.resume().unwrap(), Stop::Finished);
+    }
+
+    #[test]
+    fn watchpoints_in_one_step() {
+        // The swap changes both watched cells in one step.
+        let mut debugger = debugger(b">- >- +- >- ~- >-");
+        let (cell, other,) = (Coord::new(2, 0), Coord::new(4, 0),);
+        assert!(debugger.add_watchpoint(cell));
+        assert!(debugger.add_watchpoint(other));
+        assert!(matches!(debugger.resume().unwrap(), Stop::Watchpoint(changes) if changes.len() == 1));
+        assert_eq!(debugger.resume().unwrap(), Stop::Watchpoint(vec![
+            WatchChange { coord : cell,  old : U32Cell::from('\u{2}'), new : U32Cell::ONE },
+            WatchChange { coord : other, old : U32Cell::ONE, new : U32Cell::from('\u{2}') }
+        ]));
+        assert_eq!(debugger.resume().unwrap(), Stop::Finished);
+    }
+
+    #[test]
+    fn step_back() {
+        let mut debugger = debugger(b">- >- +- >- +-");
+        debugger.runner_mut().set_history_limit(10);
+        let cell = Coord::new(2, 0);
+        assert!(debugger.add_watchpoint(cell));
+        assert!(matches!(debugger.resume().unwrap(), Stop::Watchpoint(_)));
+        assert_eq!(debugger.resume().unwrap(), Stop::Finished);
+        assert_eq!(debugger.back_to_write(cell), Stop::Stepped);
+        assert_eq!(debugger.runner().script_head(), 2);
+        assert_eq!(debugger.step(1).unwrap(), Stop::Watchpoint(vec![WatchChange { coord : cell, old : U32Cell::ONE, new : U32Cell::from('\u{2}') }]));
+        assert_eq!(debugger.step_back(2), Stop::Stepped);
+        assert_eq!(debugger.runner().script_head(), 1);
+        assert_eq!(debugger.step_back(2), Stop::HistoryStart);
+        assert_eq!(debugger.runner().steps(), 0);
+    }
+
+    #[test]
+    fn repl() {
+        let mut debugger = debugger(b">- >- >-");
+        let mut output   = Vec::new();
+        debugger.repl(&b"b 2\nstep\nc\np 1,0\nbogus\nc\nq\n"[..], &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), concat!(
+            "step 0, next [0] >-, head (r:0,ul:0)\n",
+            "(isolang) (isolang) \n",
+            "step 1, next [1] >-, head (r:1,ul:0)\n",
+            "(isolang) \n",
+            "hit breakpoint on instruction 2\n",
+            "step 2, next [2] >-, head (r:2,ul:0)\n",
+            "(isolang) (r:1,ul:0) = 1\n",
+            "(isolang) error: unknown command `bogus`, try `help`\n",
+            "(isolang) \n",
+            "script finished\n",
+            "step 3, next [3] <end>, head (r:3,ul:0)\n",
+            "(isolang) "
+        ));
+    }
+
+}
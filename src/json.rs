@@ -0,0 +1,47 @@
+//! Helpers for writing JSON output.
+
+
+use crate::world::Coord;
+use core::fmt;
+
+
+/// Displays a string as a quoted and escaped JSON string.
+pub(crate) struct JsonStr<'l>(pub &'l str);
+
+impl fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"")?;
+        for ch in self.0.chars() { match (ch) {
+            '"'  => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            ..='\u{1F}' => write!(f, "\\u{:04x}", ch as u32)?,
+            _    => write!(f, "{ch}")?
+        } }
+        write!(f, "\"")
+    }
+}
+
+
+/// Displays a [`Coord`] as a JSON object.
+pub(crate) struct JsonCoord(pub Coord);
+
+impl fmt::Display for JsonCoord {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{\"r\":{},\"ul\":{}}}", self.0.r(), self.0.ul())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_str() {
+        assert_eq!(JsonStr(">\\ \"a\"\n\u{1}é").to_string(), "\">\\\\ \\\"a\\\"\\n\\u0001é\"");
+    }
+
+}
@@ -0,0 +1,37 @@
+//! # Isolang
+//! Parser & Interpreter
+//!
+//! Isolang scripts are parsed into a list of [`Ins`] by a [`ScriptParser`],
+//! then run by a [`ScriptRunner`] against a hexagonal [`World`] of [`Cell`]s.
+//!
+//! ```
+//! use isolang::{ ScriptParser, ScriptRunner };
+//! use isolang::world::cell::U32Cell;
+//!
+//! // Reads one character of input and writes it back out.
+//! let script = ScriptParser::parse(b">\\ ~\\ >/! ~- >\\! ~/ >-".iter().copied().map(Ok)).unwrap();
+//! let (mut runner, output) = ScriptRunner::<U32Cell>::in_memory(script, "x");
+//! while (runner.run_next().unwrap()) { }
+//! assert_eq!(output.contents(), b"x");
+//! ```
+
+
+pub mod parser;
+pub use parser::{ ScriptParser, ParseError };
+
+pub mod printer;
+pub use printer::{ ScriptPrinter, PrintError };
+
+pub mod world;
+pub use world::{ World, Cell };
+
+pub mod runner;
+pub use runner::{ ScriptRunner, RuntimeError, RuntimeErrorKind };
+pub use runner::ins::Ins;
+
+pub mod debugger;
+pub use debugger::Debugger;
+
+pub mod analysis;
+
+mod json;
@@ -1,57 +1,263 @@
 //! # Isolang
-//! Parser & Interpreter
-//!
-//! TODO: Crate docs
+//! Command line interface to the Isolang parser & interpreter.
 
-#![feature(assert_matches)]
 
-use std::fs::File;
-use std::io::{ Read, BufReader };
+use std::fs;
+use std::io::{ self, IsTerminal };
+use std::path::Path;
+use std::process::ExitCode;
 
 
+use isolang::{ ScriptParser, ScriptRunner, ScriptPrinter, Debugger, Ins, Cell };
+use isolang::parser::ParseOutput;
+use isolang::world::{ ArithMode, cell };
+use isolang::runner::TraceWriter;
+use isolang::analysis::{ ControlFlowGraph, ValueAnalysis };
+
 mod cli;
-pub use cli::*;
+use cli::*;
 
-mod parser;
-pub use parser::*;
 
-mod world;
-pub use world::*;
+fn main() -> ExitCode {
+    match (Cli::parse().cmd) {
 
-mod runner;
-pub use runner::*;
+        CliCommand::Run { parse_args, run_args, source_file } => {
+            let Some((path, source)) = read_source(&source_file)
+                else { return ExitCode::FAILURE; };
+            let output = ScriptParser::parse_all(source.iter().copied().map(Ok), (&parse_args).into());
+            if (! report_parse_output(&output, &path, &source)) {
+                return ExitCode::FAILURE;
+            }
+            let script = output.script;
+            match (run_args.cell_mode) {
+                CellMode::U8  => run_script::<cell::U8Cell>(script, &run_args),
+                CellMode::U32 => run_script::<cell::U32Cell>(script, &run_args),
+                CellMode::I8  => run_script::<cell::I8Cell>(script, &run_args),
+                CellMode::I32 => run_script::<cell::I32Cell>(script, &run_args),
+                CellMode::I64 => run_script::<cell::I64Cell>(script, &run_args),
+                CellMode::Big => run_script::<cell::BigCell>(script, &run_args)
+            }
+        },
 
+        CliCommand::Debug { parse_args, run_args, input, history, source_file } => {
+            let Some((path, source)) = read_source(&source_file)
+                else { return ExitCode::FAILURE; };
+            let output = ScriptParser::parse_all(source.iter().copied().map(Ok), (&parse_args).into());
+            if (! report_parse_output(&output, &path, &source)) {
+                return ExitCode::FAILURE;
+            }
+            let input = match (input) {
+                Some(input) => {
+                    let Some((_, input)) = read_source(&input)
+                        else { return ExitCode::FAILURE; };
+                    input
+                },
+                None => Vec::new()
+            };
+            let script = output.script;
+            match (run_args.cell_mode) {
+                CellMode::U8  => debug_script::<cell::U8Cell>(script, &run_args, input, history),
+                CellMode::U32 => debug_script::<cell::U32Cell>(script, &run_args, input, history),
+                CellMode::I8  => debug_script::<cell::I8Cell>(script, &run_args, input, history),
+                CellMode::I32 => debug_script::<cell::I32Cell>(script, &run_args, input, history),
+                CellMode::I64 => debug_script::<cell::I64Cell>(script, &run_args, input, history),
+                CellMode::Big => debug_script::<cell::BigCell>(script, &run_args, input, history)
+            }
+        },
 
-fn main() -> Result<(), ParseError> {
-    match (Cli::parse().cmd) {
+        CliCommand::Check { parse_args, cell_mode, arith_mode, source_file } => {
+            let Some((path, source)) = read_source(&source_file)
+                else { return ExitCode::FAILURE; };
+            let output = ScriptParser::parse_all(source.iter().copied().map(Ok), (&parse_args).into());
+            if (! report_parse_output(&output, &path, &source)) {
+                return ExitCode::FAILURE;
+            }
+            let arith_mode = arith_mode.into();
+            let warned = match (cell_mode) {
+                CellMode::U8  => check_script::<cell::U8Cell>(&output, arith_mode, &path, &source),
+                CellMode::U32 => check_script::<cell::U32Cell>(&output, arith_mode, &path, &source),
+                CellMode::I8  => check_script::<cell::I8Cell>(&output, arith_mode, &path, &source),
+                CellMode::I32 => check_script::<cell::I32Cell>(&output, arith_mode, &path, &source),
+                CellMode::I64 => check_script::<cell::I64Cell>(&output, arith_mode, &path, &source),
+                CellMode::Big => check_script::<cell::BigCell>(&output, arith_mode, &path, &source)
+            };
+            if (warned) { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+        },
 
-        CliCommand::Run {
-            cell_mode,
-            #[cfg(debug_assertions)]
-            world_margin,
-            source_file
-        } => {
-            let file   = File::open(source_file)?;
-            let bytes  = BufReader::new(file).bytes();
-            let script = ScriptParser::parse(bytes)?;
-            // println!("{:#?}", script);
-            match (cell_mode) {
-                CellMode::U8 => {
-                    let mut runner = ScriptRunner::<cell::U8Cell>::new(script);
-                    #[cfg(debug_assertions)]
-                    runner.world_mut().set_display_margin(world_margin);
-                    while (runner.run_next()) { }
-                },
-                CellMode::U32 => {
-                    let mut runner = ScriptRunner::<cell::U32Cell>::new(script);
-                    #[cfg(debug_assertions)]
-                    runner.world_mut().set_display_margin(world_margin);
-                    while (runner.run_next()) { }
+        CliCommand::Fmt { parse_args, check, source_file } => {
+            let Some((path, source)) = read_source(&source_file)
+                else { return ExitCode::FAILURE; };
+            let output = ScriptParser::parse_all(source.iter().copied().map(Ok), (&parse_args).into());
+            if (! report_parse_output(&output, &path, &source)) {
+                return ExitCode::FAILURE;
+            }
+            let formatted = match (ScriptPrinter::format(&output, &source)) {
+                Ok(formatted) => formatted,
+                Err(err)      => {
+                    eprintln!("error: {err}");
+                    return ExitCode::FAILURE;
                 }
+            };
+            if (formatted == source) {
+                ExitCode::SUCCESS
+            } else if (check) {
+                eprintln!("error: `{path}` is not formatted");
+                ExitCode::FAILURE
+            } else if let Err(err) = fs::write(&source_file, formatted) {
+                eprintln!("error: failed to write `{path}`: {err}");
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+
+    }
+}
+
+
+/// Exit code used when a script raises a [`RuntimeError`].
+const EXIT_RUNTIME_ERROR : u8 = 2;
+
+/// Exit code used when a script runs out of steps or time.
+const EXIT_LIMIT_REACHED : u8 = 3;
+
+
+/// Runs a script to completion, reporting any [`RuntimeError`] to stderr.
+fn run_script<C : Cell>(script : Vec<Ins>, run_args : &RunArgs) -> ExitCode {
+    let is_random  = script.iter().any(Ins::is_random);
+    let seed       = run_args.seed.unwrap_or_else(rand::random);
+    let mut runner = ScriptRunner::<C>::new(script);
+    if (! configure_runner(&mut runner, run_args, seed)) {
+        return ExitCode::FAILURE;
+    }
+    let result = runner.run();
+    println!();
+    match (result) {
+        Ok(())   => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            if (is_random) {
+                eprintln!("note: random choices were made with `--seed {seed}`");
             }
-            println!();
-            Ok(())
+            ExitCode::from(if (err.kind.is_limit()) { EXIT_LIMIT_REACHED } else { EXIT_RUNTIME_ERROR })
         }
+    }
+}
 
+/// Runs a script in the interactive debugger, reading commands from stdin.
+///
+/// The script reads from `input`, as stdin is used for commands.
+/// The most recent `history` steps can be undone.
+fn debug_script<C : Cell>(script : Vec<Ins>, run_args : &RunArgs, input : Vec<u8>, history : usize) -> ExitCode {
+    let seed       = run_args.seed.unwrap_or_else(rand::random);
+    let mut runner = ScriptRunner::<C>::with_io(script, io::Cursor::new(input), io::stdout());
+    if (! configure_runner(&mut runner, run_args, seed)) {
+        return ExitCode::FAILURE;
+    }
+    runner.set_history_limit(history);
+    let format = run_args.world_format(io::stdout().is_terminal());
+    runner.world_mut().set_display_format(format);
+    if (runner.script().iter().any(Ins::is_random)) {
+        println!("random choices are made with `--seed {seed}`");
+    }
+    match (Debugger::new(runner).repl(io::stdin().lock(), io::stdout())) {
+        Ok(())   => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Applies the options in `run_args` to a runner, reporting any error to stderr.
+///
+/// ### Returns
+/// Returns `false` if the world dump file could not be created.
+fn configure_runner<C : Cell>(runner : &mut ScriptRunner<C>, run_args : &RunArgs, seed : u64) -> bool {
+    runner.set_seed(seed);
+    runner.set_arith_mode(run_args.arith_mode.into());
+    runner.set_engine(run_args.engine.into());
+    runner.world_mut().set_eof_policy(run_args.eof.into());
+    runner.set_step_limit(run_args.max_steps);
+    runner.set_timeout(run_args.timeout);
+    runner.set_cycle_detection(run_args.detect_cycles);
+    if let Some(format) = run_args.trace {
+        runner.set_tracer(Some(Box::new(TraceWriter::new(io::stderr(), format.into()))));
+    }
+    runner.world_mut().set_display_margin(run_args.world_margin);
+    runner.world_mut().set_display_chars(run_args.world_chars);
+    runner.world_mut().set_display_format(run_args.world_format(match (&run_args.dump_world) {
+        DumpTarget::Off | DumpTarget::File(_) => false,
+        DumpTarget::Stdout                    => io::stdout().is_terminal(),
+        DumpTarget::Stderr                    => io::stderr().is_terminal()
+    }));
+    runner.set_dump_output(match (&run_args.dump_world) {
+        DumpTarget::Off        => None,
+        DumpTarget::Stdout     => Some(Box::new(io::stdout())),
+        DumpTarget::Stderr     => Some(Box::new(io::stderr())),
+        DumpTarget::File(path) => match (fs::File::create(path)) {
+            Ok(file) => Some(Box::new(io::BufWriter::new(file))),
+            Err(err) => {
+                eprintln!("error: failed to create `{}`: {err}", path.display());
+                return false;
+            }
+        }
+    });
+    true
+}
+
+
+/// Analyses the control flow and cell values of a parsed script, reporting anything found to stderr.
+///
+/// ### Returns
+/// Returns `true` if any warning was reported. Notes do not count.
+fn check_script<C : Cell>(output : &ParseOutput, arith_mode : ArithMode, path : &str, source : &[u8]) -> bool {
+    let cfg = ControlFlowGraph::build::<C>(&output.script, arith_mode);
+    let mut warned = false;
+    for diagnostic in cfg.diagnostics() {
+        let (start, end,) = diagnostic.indices();
+        let span  = output.spans[start].to(output.spans[end - 1]);
+        let level = if (diagnostic.is_note()) { "note" } else { warned = true; "warning" };
+        eprintln!("{level}: {diagnostic}\n{}\n", span.snippet(path, source));
+    }
+    let values = ValueAnalysis::analyze::<C>(&output.script, arith_mode);
+    for diagnostic in values.diagnostics() {
+        eprintln!("warning: {diagnostic}\n{}\n", output.spans[diagnostic.index()].snippet(path, source));
+        warned = true;
+    }
+    warned
+}
+
+
+/// Reads a source file, reporting any error to stderr.
+///
+/// ### Returns
+/// Returns the displayable path and the contents of the file, or `None` if it could not be read.
+fn read_source(source_file : &Path) -> Option<(String, Vec<u8>,)> {
+    let path = source_file.display().to_string();
+    match (fs::read(source_file)) {
+        Ok(source) => Some((path, source,)),
+        Err(err)   => {
+            eprintln!("error: failed to read `{path}`: {err}");
+            None
+        }
+    }
+}
+
+/// Prints the errors and warnings from parsing a script to stderr,
+/// each with a snippet of the source pointing at it.
+///
+/// ### Returns
+/// Returns `true` if there were no errors.
+fn report_parse_output(output : &ParseOutput, path : &str, source : &[u8]) -> bool {
+    for warning in &output.warnings {
+        eprintln!("warning: {warning}\n{}\n", warning.span().snippet(path, source));
+    }
+    for err in &output.errors {
+        eprintln!("error: {err}\n{}\n", err.span().snippet(path, source));
+    }
+    if (! output.errors.is_empty()) {
+        eprintln!("error: found {} error(s) in `{path}`", output.errors.len());
     }
+    output.errors.is_empty()
 }
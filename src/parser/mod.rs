@@ -2,34 +2,167 @@
 
 
 use crate::world::{ Adj, Dir };
-use crate::runner::ins::{ Ins, InsMod, InsModKind, BadInvertError };
+use crate::runner::ins::{ Ins, InsMod, InsModKind };
+use core::fmt;
+use std::error::Error;
 use std::io;
 use std::iter::Peekable;
 
 
+mod span;
+pub use span::{ Span, Snippet };
+
+
 /// Isolang script parser.
 pub struct ScriptParser<F : Iterator<Item = io::Result<u8>>> {
+
     /// An iterator over the bytes to parse.
-    f : Peekable<F>
+    f          : Peekable<F>,
+
+    /// The position of the next unread character.
+    pos        : Span,
+
+    /// The options to parse with.
+    options    : ParseOptions,
+
+    /// Whether ignored prose was found on the current line.
+    line_prose : bool,
+
+    /// The first instruction found on the current line.
+    line_ins   : Option<Span>,
+
+    /// The warnings found so far.
+    warnings   : Vec<ParseWarning>,
+
+    /// The comments found so far.
+    comments   : Vec<Span>,
+
+    /// The runs of ignored prose found so far.
+    prose      : Vec<Span>,
+
+    /// Whether the last thing found on the current line was ignored prose, which the next prose continues.
+    prose_open : bool
+
 }
 
 impl<F : Iterator<Item = io::Result<u8>>> ScriptParser<F> {
 
-    /// Parse a script into a [`Vec`] of instructions.
+    /// Creates a new parser over some bytes.
+    fn new(f : F, options : ParseOptions) -> Self { Self {
+        f          : f.peekable(),
+        pos        : Span::START,
+        options,
+        line_prose : false,
+        line_ins   : None,
+        warnings   : Vec::new(),
+        comments   : Vec::new(),
+        prose      : Vec::new(),
+        prose_open : false
+    } }
+
+    /// Parse a script into a [`Vec`] of instructions, using the default [`ParseOptions`].
     /// 
     /// ### Returns
     /// Returns:
     /// - `Ok(_)` if the script was successfully parsed.
     /// - `Err(_)` if some other error occured.
     pub fn parse(f : F) -> Result<Vec<Ins>, ParseError> {
-        let mut parser = Self { f : f.peekable() };
+        let mut parser = Self::new(f, ParseOptions::default());
         let mut script = Vec::new();
-        while let Some(ins) = parser.parse_ins()? {
+        while let Some((ins, _,)) = parser.parse_ins()? {
             script.push(ins);
         }
         Ok(script)
     }
 
+    /// Parse a script into a [`Vec`] of instructions, recovering from errors.
+    ///
+    /// After an error, the parser skips ahead to the next instruction character and
+    /// continues, so every problem in the script is reported in one pass.
+    ///
+    /// ### Returns
+    /// Returns a [`ParseOutput`] containing every instruction that could be parsed,
+    /// and every error and warning found. Parsing stops at the first IO error.
+    pub fn parse_all(f : F, options : ParseOptions) -> ParseOutput {
+        let mut parser = Self::new(f, options);
+        let mut output = ParseOutput::default();
+        loop {
+            match (parser.parse_ins()) {
+                Ok(Some((ins, span,))) => {
+                    output.script.push(ins);
+                    output.spans.push(span);
+                },
+                Ok(None) => { break; },
+                Err(err @ ParseError::Io { .. }) => {
+                    output.errors.push(err);
+                    break;
+                },
+                Err(err) => {
+                    output.errors.push(err);
+                    if let Err(err) = parser.recover() {
+                        output.errors.push(err);
+                        break;
+                    }
+                }
+            }
+        }
+        output.warnings = parser.warnings;
+        output.comments = parser.comments;
+        output.prose    = parser.prose;
+        output
+    }
+
+}
+
+
+/// Options which change how scripts are parsed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions {
+
+    /// How characters which are not part of any instruction or comment are handled.
+    pub unknown_chars : UnknownChars
+
+}
+
+/// How characters which are not part of any instruction or comment are handled.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum UnknownChars {
+
+    /// Unknown characters are errors.
+    #[default]
+    Reject,
+
+    /// Unknown characters are skipped, allowing prose between instructions.
+    ///
+    /// A warning is raised for each line which mixes prose and instructions.
+    Ignore
+
+}
+
+
+/// The result of [`ScriptParser::parse_all`].
+#[derive(Debug, Default)]
+pub struct ParseOutput {
+
+    /// The instructions that were successfully parsed.
+    pub script   : Vec<Ins>,
+
+    /// The position of each instruction in `script`, including its modifiers.
+    pub spans    : Vec<Span>,
+
+    /// The errors found while parsing, in source order.
+    pub errors   : Vec<ParseError>,
+
+    /// The warnings found while parsing, in source order.
+    pub warnings : Vec<ParseWarning>,
+
+    /// The position of each comment, including its delimiters, in source order.
+    pub comments : Vec<Span>,
+
+    /// The position of each run of ignored prose on a line, in source order.
+    /// Always empty unless parsed with [`UnknownChars::Ignore`].
+    pub prose    : Vec<Span>
+
 }
 
 
@@ -39,13 +172,21 @@ impl<F : Iterator<Item = io::Result<u8>>> ScriptParser<F> {
     /// 
     /// # Returns
     /// Returns
-    /// - `Ok(Some(_))` if a character was found.
+    /// - `Ok(Some(_))` if a character was found, along with its position.
     /// - `Ok(None)` if the end of the file has been reached.
     /// - `Err(_)` if some other error occured.
-    fn next_char(&mut self) -> Result<Option<char>, ParseError> {
+    fn next_char(&mut self) -> Result<Option<(char, Span)>, ParseError> {
         let Some(ch) = self.f.next()
             else { return Ok(None); };
-        Ok(Some(ch? as char))
+        let ch   = ch.map_err(|err| ParseError::Io { err, span : self.pos })? as char;
+        let span = Span { end : self.pos.start + 1, ..self.pos };
+        self.pos = span.after();
+        if (ch == '\n') {
+            self.pos.line   += 1;
+            self.pos.column  = 1;
+            self.end_line();
+        }
+        Ok(Some((ch, span)))
     }
 
     /// Gets the next unread character without marking it as read.
@@ -60,7 +201,7 @@ impl<F : Iterator<Item = io::Result<u8>>> ScriptParser<F> {
     fn peek_char(&mut self) -> Result<Option<char>, ParseError> {
         match (self.f.peek()) {
             Some(Ok(b))  => Ok(Some(*b as char)),
-            Some(Err(_)) => Err(ParseError::Io(self.f.next().unwrap().unwrap_err())),
+            Some(Err(_)) => Err(ParseError::Io { err : self.f.next().unwrap().unwrap_err(), span : self.pos }),
             None         => Ok(None)
         }
     }
@@ -68,12 +209,48 @@ impl<F : Iterator<Item = io::Result<u8>>> ScriptParser<F> {
     /// Marks the next character as read.
     /// 
     /// ### Returns
+    /// Returns:
+    /// - `Ok(_)` with the position of the skipped character.
+    /// - `Err(_)` if some other error occured.
+    fn skip_char(&mut self) -> Result<Span, ParseError> {
+        Ok(self.next_char()?.map_or(self.pos, |(_, span,)| span))
+    }
+
+    /// Skips characters until the next instruction character or the end of the file.
+    ///
+    /// Comments are skipped entirely, so instruction characters inside them are not used.
+    ///
+    /// ### Returns
     /// Returns `Err(_)` if some other error occured.
-    fn skip_char(&mut self) -> Result<(), ParseError> {
-        let _ = self.next_char()?;
+    fn recover(&mut self) -> Result<(), ParseError> {
+        while let Some(ch) = self.peek_char()? {
+            if (is_ins_char(ch)) { break; }
+            let span = self.skip_char()?;
+            if (ch == '%') { self.parse_comment(span)?; }
+        }
         Ok(())
     }
 
+    /// Finishes the current line, warning if it mixed prose and instructions.
+    fn end_line(&mut self) {
+        if let (true, Some(span),) = (self.line_prose, self.line_ins,) {
+            self.warnings.push(ParseWarning::InsInProse { span });
+        }
+        self.line_prose = false;
+        self.line_ins   = None;
+        self.prose_open = false;
+    }
+
+    /// Marks the character at `span` as ignored prose, continuing the last run of prose if nothing came between.
+    fn ignore(&mut self, span : Span) {
+        self.line_prose = true;
+        match (self.prose.last_mut()) {
+            Some(prose) if (self.prose_open) => { prose.end = span.end; },
+            _ => { self.prose.push(span); }
+        }
+        self.prose_open = true;
+    }
+
 }
 
 
@@ -83,34 +260,101 @@ impl<F : Iterator<Item = io::Result<u8>>> ScriptParser<F> {
     /// 
     /// # Returns
     /// Returns
-    /// - `Ok(Some(_))` if an instruction was successfully parsed.
+    /// - `Ok(Some(_))` if an instruction was successfully parsed, along with its position.
     /// - `Ok(None)` if there is no instruction.
     /// - `Err(_)` if some other error occured.
-    fn parse_ins(&mut self) -> Result<Option<Ins>, ParseError> {
+    fn parse_ins(&mut self) -> Result<Option<(Ins, Span,)>, ParseError> {
         loop {
-            let Some(ch) = self.next_char()?
-                else { return Ok(None); };
+            let Some((ch, span)) = self.next_char()?
+                else {
+                    self.end_line();
+                    return Ok(None);
+                };
             let mut ins = match (ch) {
-                '+' => Ins::Add { adj : self.parse_adj()? },
-                '*' => Ins::Mul { adj : self.parse_adj()? },
-                '~' => Ins::Swap { adj : self.parse_adj()? },
-                '>' => Ins::MoveHeadOne { adj : self.parse_adj()?, dir : Dir::R },
-                ';' => Ins::MoveHeadDynamic { adj: self.parse_adj()?, dir: Dir::R },
+                '+' | '*' | '~' | '>' | ';' => {
+                    let Some(adj) = self.parse_ins_adj(span)?
+                        else { continue; };
+                    match (ch) {
+                        '+' => Ins::Add { adj },
+                        '*' => Ins::Mul { adj },
+                        '~' => Ins::Swap { adj },
+                        '>' => Ins::MoveHeadOne { adj, dir : Dir::R },
+                        _   => Ins::MoveHeadDynamic { adj, dir : Dir::R }
+                    }
+                },
                 ':' => Ins::JumpThruCode { dir: Dir::R },
-                #[cfg(debug_assertions)]
                 '@' => Ins::DumpWorld,
-                ' '|'\n'|'\t'|'\r' => { continue; }
-                _   => {
-                    #[cfg(debug_assertions)]
-                    return Err(ParseError::BadChar(ch));
-                    #[cfg(not(debug_assertions))]
+                '%' => {
+                    self.prose_open = false;
+                    self.parse_comment(span)?;
                     continue;
+                },
+                ' '|'\n'|'\t'|'\r' => { continue; }
+                _   => match (self.options.unknown_chars) {
+                    UnknownChars::Reject => { return Err(ParseError::BadChar { ch, span }); },
+                    UnknownChars::Ignore => {
+                        self.ignore(span);
+                        continue;
+                    }
                 }
             };
-            while let Some(ins_mod) = self.parse_ins_mod()? {
-                ins = ins.modify(ins_mod)?;
+            while let Some((ins_mod, mod_span,)) = self.parse_ins_mod()? {
+                ins = ins.modify(ins_mod)
+                    .map_err(|_| ParseError::BadInvert { span : span.to(mod_span) })?;
+            }
+            let span = Span { end : self.pos.start, ..span };
+            self.line_ins.get_or_insert(span);
+            self.prose_open = false;
+            return Ok(Some((ins, span,)));
+        }
+    }
+
+    /// Parses the rest of a comment, started by the `%` at `start`.
+    ///
+    /// `%{` starts a comment which runs until the next `%}`,
+    /// and any other `%` starts a comment which runs until the end of the line.
+    ///
+    /// *Note: `%` is not an instruction, adj or modifier character, so a comment can follow anything.*
+    ///
+    /// ### Returns
+    /// Returns `Err(_)` if a block comment is not terminated, or some other error occured.
+    fn parse_comment(&mut self, start : Span) -> Result<(), ParseError> {
+        if (self.peek_char()? == Some('{')) {
+            self.skip_char()?;
+            loop {
+                let Some((ch, _,)) = self.next_char()?
+                    else { return Err(ParseError::UnterminatedComment { span : start.to(self.pos) }); };
+                if (ch == '%' && self.peek_char()? == Some('}')) {
+                    self.skip_char()?;
+                    break;
+                }
+            }
+        } else {
+            while let Some(ch) = self.peek_char()? {
+                if (ch == '\n') { break; }
+                self.skip_char()?;
             }
-            return Ok(Some(ins));
+        }
+        self.comments.push(Span { end : self.pos.start, ..start });
+        Ok(())
+    }
+
+    /// Parses the adj of the instruction started by the character at `start`.
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Ok(Some(_))` if an adj was successfully parsed.
+    /// - `Ok(None)` if there is no adj and unknown characters are ignored,
+    ///   so the instruction character was part of some prose.
+    /// - `Err(_)` if some other error occured.
+    fn parse_ins_adj(&mut self, start : Span) -> Result<Option<Adj>, ParseError> {
+        match (self.parse_adj()) {
+            Ok(adj) => Ok(Some(adj)),
+            Err(ParseError::BadChar { .. } | ParseError::BadEOF { .. }) if (self.options.unknown_chars == UnknownChars::Ignore) => {
+                self.ignore(start);
+                Ok(None)
+            },
+            Err(err) => Err(err)
         }
     }
 
@@ -121,79 +365,246 @@ impl<F : Iterator<Item = io::Result<u8>>> ScriptParser<F> {
     /// - `Ok(_)` if an adj was successfully parsed.
     /// - `Err(_)` if some other error occured.
     fn parse_adj(&mut self) -> Result<Adj, ParseError> {
-        let Some(ch) = self.next_char()?
-            else { return Err(ParseError::BadEOF); };
-        Ok(match (ch) {
+        let Some(ch) = self.peek_char()?
+            else { return Err(ParseError::BadEOF { span : self.pos }); };
+        let adj = match (ch) {
             '\\' => Adj::ULDR,
             '/' => Adj::DLUR,
             '-' => Adj::LR,
             '^' => Adj::D2,
             'v' => Adj::U2,
-            _ => return Err(ParseError::BadChar(ch))
-        })
+            // Left unread, in case it starts the next instruction.
+            _ => return Err(ParseError::BadChar { ch, span : Span { end : self.pos.start + 1, ..self.pos } })
+        };
+        self.skip_char()?;
+        Ok(adj)
     }
 
     /// Parses a single instruction modifier.
     /// 
     /// ### Returns
     /// Returns:
-    /// - `Ok(Some(_))` if an instruction modifier was successfully parsed.
+    /// - `Ok(Some(_))` if an instruction modifier was successfully parsed, along with its position.
     /// - `Ok(None)` if no instruction modifier was parsed.
     /// - `Err())` if some other error occured.
-    fn parse_ins_mod(&mut self) -> Result<Option<InsMod>, ParseError> {
+    fn parse_ins_mod(&mut self) -> Result<Option<(InsMod, Span,)>, ParseError> {
         let Some(ch) = self.peek_char()?
             else { return Ok(None); };
         if (ch == '#') {
-            self.skip_char()?;
-            Ok(Some(InsMod {
+            let span = self.skip_char()?;
+            Ok(Some((InsMod {
                 kind         : InsModKind::Skip,
                 random_maybe : true
-            }))
+            }, span,)))
         } else {
             let kind = match (ch) {
                 '?' => InsModKind::IfNotZeroCond,
                 '!' => InsModKind::Invert,
                 _   => { return Ok(None); }
             };
-            self.skip_char()?;
+            let mut span = self.skip_char()?;
             let mut random_maybe = false;
             if let Some('#') = self.peek_char()? {
                 random_maybe = true;
-                self.skip_char()?;
+                span = span.to(self.skip_char()?);
             }
-            Ok(Some(InsMod { kind, random_maybe }))
+            Ok(Some((InsMod { kind, random_maybe }, span,)))
         }
     }
 
 }
 
 
+/// Whether a character starts an instruction.
+fn is_ins_char(ch : char) -> bool {
+    matches!(ch, '+' | '*' | '~' | '>' | ';' | ':' | '@')
+}
+
+
 /// An error raised while parsing an Isolang script.
 #[derive(Debug)]
 pub enum ParseError {
 
     /// Some IO-related error occured.
-    Io(io::Error),
+    Io {
+        /// The underlying error.
+        err  : io::Error,
+        /// Where in the source the read failed.
+        span : Span
+    },
 
     /// The end of the file was found, but not all required arguments were provided.
-    BadEOF,
+    BadEOF {
+        /// The end of the file.
+        span : Span
+    },
 
     /// An unexpected character was found.
-    BadChar(char),
+    BadChar {
+        /// The unexpected character.
+        ch   : char,
+        /// Where the character was found.
+        span : Span
+    },
 
     /// An instruction that can not be inverted was inverted.
-    BadInvert
+    BadInvert {
+        /// The instruction and its modifiers, up to the offending invert.
+        span : Span
+    },
+
+    /// The end of the file was found inside of a `%{` comment.
+    UnterminatedComment {
+        /// The comment, from its start to the end of the file.
+        span : Span
+    }
 
 }
 
-/// Allows using the `?` operator on `Err(io::Error)` types to auto-convert them to [`ParseError`].
-impl From<io::Error> for ParseError {
-    fn from(err : io::Error) -> Self { Self::Io(err) }
+impl ParseError {
+
+    /// Where in the source this error occured.
+    pub fn span(&self) -> Span { match (self) {
+        Self::Io        { span, .. } => *span,
+        Self::BadEOF    { span }     => *span,
+        Self::BadChar   { span, .. } => *span,
+        Self::BadInvert { span }     => *span,
+        Self::UnterminatedComment { span } => *span
+    } }
+
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::Io        { err, .. } => write!(f, "failed to read script: {err}"),
+        Self::BadEOF    { .. }      => write!(f, "unexpected end of file, expected an adj"),
+        Self::BadChar   { ch, .. }  => write!(f, "unexpected character `{}`", ch.escape_debug()),
+        Self::BadInvert { .. }      => write!(f, "instruction can not be inverted"),
+        Self::UnterminatedComment { .. } => write!(f, "unterminated block comment, expected `%}}`")
+    } }
 }
 
-/// Allows using the `?` operator on `Err(BadInvertError)` types to auto-convert them to [`ParseError`].
-impl From<BadInvertError> for ParseError {
-    fn from(_ : BadInvertError) -> Self { Self::BadInvert }
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> { match (self) {
+        Self::Io { err, .. } => Some(err),
+        _                    => None
+    } }
 }
 
-// TODO tests
\ No newline at end of file
+
+/// A problem found while parsing an Isolang script, which does not stop it from being parsed.
+#[derive(Debug)]
+pub enum ParseWarning {
+
+    /// An instruction was found on a line which also contains ignored prose.
+    ///
+    /// This usually means that the prose accidentally contains instruction characters.
+    InsInProse {
+        /// The first instruction on the line.
+        span : Span
+    }
+
+}
+
+impl ParseWarning {
+
+    /// Where in the source this warning occured.
+    pub fn span(&self) -> Span { match (self) {
+        Self::InsInProse { span } => *span
+    } }
+
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::InsInProse { .. } => write!(f, "instruction found on a line of ignored prose")
+    } }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a script from a string.
+    fn parse(src : &str) -> Result<Vec<Ins>, ParseError> {
+        ScriptParser::parse(src.bytes().map(Ok))
+    }
+
+    #[test]
+    fn parse_simple() {
+        assert_eq!(parse(">-\n+v! @").unwrap(), vec![
+            Ins::MoveHeadOne { adj : Adj::LR, dir : Dir::R },
+            Ins::Sub { adj : Adj::U2 },
+            Ins::DumpWorld
+        ]);
+    }
+
+    #[test]
+    fn bad_char_span() {
+        let err = parse(">-\n+x").unwrap_err();
+        assert!(matches!(err, ParseError::BadChar { ch : 'x', .. }));
+        assert_eq!(err.span(), Span { start : 4, end : 5, line : 2, column : 2 });
+    }
+
+    #[test]
+    fn bad_eof_span() {
+        let err = parse(">-\n~").unwrap_err();
+        assert!(matches!(err, ParseError::BadEOF { .. }));
+        assert_eq!(err.span(), Span { start : 4, end : 4, line : 2, column : 2 });
+    }
+
+    #[test]
+    fn bad_invert_span() {
+        let err = parse("  ~-!").unwrap_err();
+        assert!(matches!(err, ParseError::BadInvert { .. }));
+        assert_eq!(err.span(), Span { start : 2, end : 5, line : 1, column : 3 });
+    }
+
+    #[test]
+    fn parse_all_recovers() {
+        let output = ScriptParser::parse_all(">x!#\n~-!?\n+v\n*".bytes().map(Ok), ParseOptions::default());
+        assert_eq!(output.script, vec![Ins::Add { adj : Adj::U2 }]);
+        let lines = output.errors.iter().map(|err| err.span().line).collect::<Vec<_>>();
+        assert_eq!(lines, vec![1, 2, 4]);
+        assert!(matches!(output.errors[0], ParseError::BadChar { ch : 'x', .. }));
+        assert!(matches!(output.errors[1], ParseError::BadInvert { .. }));
+        assert!(matches!(output.errors[2], ParseError::BadEOF { .. }));
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(parse("% +x\n>- % ~\n%{ :\n %} +v %{%}").unwrap(), vec![
+            Ins::MoveHeadOne { adj : Adj::LR, dir : Dir::R },
+            Ins::Add { adj : Adj::U2 }
+        ]);
+        // Comments can directly follow an adj or modifier, even one which is `/`.
+        assert_eq!(parse(">- >- +/% note\n>-!%{ note %}").unwrap(), vec![
+            Ins::MoveHeadOne { adj : Adj::LR, dir : Dir::R },
+            Ins::MoveHeadOne { adj : Adj::LR, dir : Dir::R },
+            Ins::Add { adj : Adj::DLUR },
+            Ins::MoveHeadOne { adj : Adj::LR, dir : Dir::L }
+        ]);
+        assert!(matches!(parse(">- %{ +-").unwrap_err(), ParseError::UnterminatedComment { .. }));
+        assert!(matches!(parse(">- / +-").unwrap_err(), ParseError::BadChar { ch : '/', .. }));
+    }
+
+    #[test]
+    fn unknown_chars() {
+        let options = ParseOptions { unknown_chars : UnknownChars::Ignore };
+        let output  = ScriptParser::parse_all("What I thought\n>-\nmove >- here\n".bytes().map(Ok), options);
+        assert!(output.errors.is_empty());
+        assert_eq!(output.script.len(), 2);
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].span(), Span { start : 23, end : 25, line : 3, column : 6 });
+        assert!(matches!(parse("What I thought").unwrap_err(), ParseError::BadChar { ch : 'W', .. }));
+        // Instruction characters without an adj are prose too.
+        let output = ScriptParser::parse_all("add one + two\nthen>- % note\n>".bytes().map(Ok), options);
+        assert!(output.errors.is_empty());
+        assert_eq!(output.script, vec![Ins::MoveHeadOne { adj : Adj::LR, dir : Dir::R }]);
+        let prose = output.prose.iter().map(|span| (span.start, span.end,)).collect::<Vec<_>>();
+        assert_eq!(prose, vec![(0, 13,), (14, 18,), (28, 29,)]);
+        assert!(matches!(parse("add one + two").unwrap_err(), ParseError::BadChar { ch : 'a', .. }));
+    }
+
+}
@@ -0,0 +1,114 @@
+//! Positions in Isolang source text.
+
+
+use core::fmt;
+
+
+/// A region of source text.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Default)]
+pub struct Span {
+
+    /// Byte offset of the first character.
+    pub start  : usize,
+
+    /// Byte offset after the last character.
+    pub end    : usize,
+
+    /// Line of the first character, starting at 1.
+    pub line   : usize,
+
+    /// Column of the first character, starting at 1.
+    pub column : usize
+
+}
+
+impl Span {
+
+    /// A zero-width span at the very start of a file.
+    pub const START : Self = Self { start : 0, end : 0, line : 1, column : 1 };
+
+    /// Creates a span covering `self` up to the end of `other`.
+    pub fn to(self, other : Span) -> Self {
+        Self { end : other.end.max(self.end), ..self }
+    }
+
+    /// Creates a zero-width span just after the end of `self`.
+    ///
+    /// *Note: This assumes that `self` does not cross a line break.*
+    pub fn after(self) -> Self {
+        Self {
+            start  : self.end,
+            end    : self.end,
+            line   : self.line,
+            column : self.column + (self.end - self.start)
+        }
+    }
+
+    /// Returns a rustc-style snippet of the source, with a caret under this span.
+    pub fn snippet<'l>(&self, path : &'l str, source : &'l [u8]) -> Snippet<'l> {
+        Snippet { span : *self, path, source }
+    }
+
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+
+/// A rustc-style source snippet with a caret under a [`Span`].
+pub struct Snippet<'l> {
+
+    /// The span to point at.
+    span   : Span,
+
+    /// The path of the source file.
+    path   : &'l str,
+
+    /// The full source text.
+    source : &'l [u8]
+
+}
+
+impl fmt::Display for Snippet<'_> {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line_no = self.span.line.to_string();
+        let gutter  = " ".repeat(line_no.len());
+        writeln!(f, "{gutter}--> {}:{}", self.path, self.span)?;
+        let Some(line) = self.source.split(|&b| b == b'\n').nth(self.span.line - 1)
+            else { return Ok(()); };
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        writeln!(f, "{gutter} |")?;
+        write!(f, "{line_no} | ")?;
+        for &b in line { write!(f, "{}", b as char)?; }
+        writeln!(f)?;
+        write!(f, "{gutter} | ")?;
+        let col = (self.span.column - 1).min(line.len());
+        for &b in &line[..col] { write!(f, "{}", if (b == b'\t') { '\t' } else { ' ' })?; }
+        let width = (self.span.end - self.span.start).clamp(1, (line.len() - col).max(1));
+        write!(f, "{}", "^".repeat(width))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_caret() {
+        let source = b">-\n+x\n";
+        let span   = Span { start : 4, end : 5, line : 2, column : 2 };
+        assert_eq!(span.snippet("a.isolang", source).to_string(), " --> a.isolang:2:2\n  |\n2 | +x\n  |  ^");
+    }
+
+    #[test]
+    fn snippet_eof() {
+        let source = b">";
+        let span   = Span { start : 1, end : 1, line : 1, column : 2 };
+        assert_eq!(span.snippet("a.isolang", source).to_string(), " --> a.isolang:1:2\n  |\n1 | >\n  |  ^");
+    }
+
+}
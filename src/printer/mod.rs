@@ -0,0 +1,291 @@
+//! Isolang script printer.
+
+
+use crate::world::{ Adj, Dir };
+use crate::parser::ParseOutput;
+use crate::runner::ins::{ Ins, InsMod, InsModKind };
+use core::fmt;
+use std::error::Error;
+
+
+/// Isolang script printer.
+///
+/// Turns instructions back into canonical Isolang source, such that parsing the
+/// source again produces the same instructions.
+pub struct ScriptPrinter;
+
+impl ScriptPrinter {
+
+    /// Print a script as canonical Isolang source, one instruction per line.
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Ok(_)` if every instruction could be printed.
+    /// - `Err(_)` if some instruction has no source form.
+    pub fn print(script : &[Ins]) -> Result<String, PrintError> {
+        let mut out = String::new();
+        for (index, ins) in script.iter().enumerate() {
+            out.push_str(&Self::print_ins(ins).ok_or(PrintError { index })?);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Print a single instruction as canonical Isolang source, including its modifiers.
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Some(_)` if the instruction could be printed.
+    /// - `None` if the instruction has no source form.
+    pub fn print_ins(ins : &Ins) -> Option<String> {
+        let (ch, adj, mods,) = Self::decompose(ins)?;
+        let mut out = String::from(ch);
+        if let Some(adj) = adj {
+            out.push(adj_char(adj));
+        }
+        let mut prev : Option<InsMod> = None;
+        for ins_mod in mods {
+            // A `#` directly after a non-random `?` or `!` would be read as its random flag.
+            if let (Some(InsMod { random_maybe : false, .. }), InsModKind::Skip,) = (prev, ins_mod.kind,) {
+                return None;
+            }
+            match (ins_mod.kind) {
+                InsModKind::Invert        => { out.push('!'); },
+                InsModKind::IfNotZeroCond => { out.push('?'); },
+                InsModKind::Skip          => { }
+            }
+            if (ins_mod.random_maybe) { out.push('#'); }
+            prev = Some(ins_mod);
+        }
+        Some(out)
+    }
+
+    /// Print a single instruction as canonical Isolang source if it has a source form,
+    /// or its [`Debug`] form otherwise.
+    pub fn describe_ins(ins : &Ins) -> String {
+        Self::print_ins(ins).unwrap_or_else(|| format!("{ins:?}"))
+    }
+
+    /// Rewrite a parsed script into canonical layout.
+    ///
+    /// Each instruction is printed on its own line in canonical form. Comments and ignored prose are kept,
+    /// either on their own line or trailing the line they were on, and runs of blank lines
+    /// between items are collapsed into one.
+    ///
+    /// The result is bytes rather than a string, as comments and prose are copied from `source` as they are,
+    /// even if they are not valid UTF-8.
+    ///
+    /// *Note: `output` should have been parsed from `source` without errors, or
+    /// the parts of the script which failed to parse will be lost.*
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Ok(_)` if every instruction could be printed.
+    /// - `Err(_)` if some instruction has no source form.
+    pub fn format(output : &ParseOutput, source : &[u8]) -> Result<Vec<u8>, PrintError> {
+        let mut items = output.spans.iter().copied().enumerate()
+            .map(|(index, span,)| (span, Some(index),))
+            .chain(output.comments.iter().chain(&output.prose).map(|&span| (span, None,)))
+            .collect::<Vec<_>>();
+        items.sort_by_key(|(span, _,)| span.start);
+
+        let mut out       = Vec::new();
+        let mut prev_line = None;
+        for (span, index,) in items {
+            let text = match (index) {
+                Some(index) => Self::print_ins(&output.script[index]).ok_or(PrintError { index })?.into_bytes(),
+                None        => source[span.start..span.end].trim_ascii_end().to_vec()
+            };
+            match (prev_line) {
+                Some(prev_line) if (index.is_none() && span.line == prev_line) => { out.push(b' '); },
+                Some(prev_line) => {
+                    if (span.line > prev_line + 1) { out.push(b'\n'); }
+                    out.push(b'\n');
+                },
+                None => { }
+            }
+            out.extend_from_slice(&text);
+            prev_line = Some(span.line + text.iter().filter(|&&byte| byte == b'\n').count());
+        }
+        if (prev_line.is_some()) { out.push(b'\n'); }
+        Ok(out)
+    }
+
+}
+
+
+impl ScriptPrinter {
+
+    /// Splits an instruction into the character and adj it starts with, and the modifiers
+    /// which turn that into the instruction.
+    ///
+    /// The fewest modifiers possible are used.
+    ///
+    /// ### Returns
+    /// Returns `None` if the instruction can not be produced by any modifiers.
+    fn decompose(ins : &Ins) -> Option<(char, Option<Adj>, Vec<InsMod>,)> {
+        /// A modifier which is always applied.
+        const fn always(kind : InsModKind) -> InsMod {
+            InsMod { kind, random_maybe : false }
+        }
+        Some(match (ins) {
+            Ins::Add  { adj } => ('+', Some(*adj), vec![]),
+            Ins::Sub  { adj } => ('+', Some(*adj), vec![always(InsModKind::Invert)]),
+            Ins::Mul  { adj } => ('*', Some(*adj), vec![]),
+            Ins::SDiv { adj } => ('*', Some(*adj), vec![always(InsModKind::Invert)]),
+            Ins::Swap { adj } => ('~', Some(*adj), vec![]),
+            Ins::MoveHeadOne { adj, dir } => ('>', Some(*adj), match (dir) {
+                Dir::R => vec![],
+                Dir::L => vec![always(InsModKind::Invert)]
+            }),
+            Ins::MoveHeadDynamic { adj, dir } => (';', Some(*adj), match (dir) {
+                Dir::R => vec![],
+                Dir::L => vec![always(InsModKind::Invert)]
+            }),
+            Ins::JumpThruCode { dir } => (':', None, match (dir) {
+                Dir::R => vec![],
+                Dir::L => vec![always(InsModKind::Invert)]
+            }),
+            Ins::DumpWorld => ('@', None, vec![]),
+            Ins::Noop => { return None; },
+            Ins::IfNotZeroCond { ins } => {
+                let (ch, adj, mut mods,) = Self::decompose(ins)?;
+                mods.push(always(InsModKind::IfNotZeroCond));
+                (ch, adj, mods,)
+            },
+            Ins::IfZeroCond { ins } => {
+                let (ch, adj, mut mods,) = Self::decompose(ins)?;
+                mods.push(always(InsModKind::IfNotZeroCond));
+                mods.push(always(InsModKind::Invert));
+                (ch, adj, mods,)
+            },
+            Ins::RandomlyChoose { options } => {
+                let (unmodified, modified,) = &**options;
+                let (ch, adj, mut mods,) = Self::decompose(unmodified)?;
+                let kind = [InsModKind::Invert, InsModKind::IfNotZeroCond, InsModKind::Skip].into_iter()
+                    .find(|&kind| unmodified.clone().modify(always(kind)).is_ok_and(|ins| &ins == modified))?;
+                mods.push(InsMod { kind, random_maybe : true });
+                (ch, adj, mods,)
+            }
+        })
+    }
+
+}
+
+
+/// The character which represents an adj in Isolang source.
+fn adj_char(adj : Adj) -> char { match (adj) {
+    Adj::ULDR => '\\',
+    Adj::DLUR => '/',
+    Adj::LR   => '-',
+    Adj::D2   => '^',
+    Adj::U2   => 'v'
+} }
+
+
+/// An instruction with no Isolang source form was printed.
+///
+/// This happens for instructions which the parser never produces, such as a bare [`Ins::Noop`].
+#[derive(Debug)]
+pub struct PrintError {
+
+    /// The index of the instruction in the script.
+    pub index : usize
+
+}
+
+impl fmt::Display for PrintError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "instruction {} has no Isolang source form", self.index)
+    }
+}
+
+impl Error for PrintError { }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ ScriptParser, ParseOptions, UnknownChars };
+    use rand::{ Rng, SeedableRng };
+    use rand::rngs::StdRng;
+
+    /// Parses a script from a string.
+    fn parse(src : &str) -> Vec<Ins> {
+        ScriptParser::parse(src.bytes().map(Ok)).unwrap()
+    }
+
+    #[test]
+    fn print_canonical() {
+        assert_eq!(ScriptPrinter::print(&parse(">-!!  +v! ;/?! :!#  ~^#?")).unwrap(), ">-\n+v!\n;/?!\n:!#\n~^#?\n");
+    }
+
+    #[test]
+    fn print_unprintable() {
+        assert_eq!(ScriptPrinter::print(&[Ins::Add { adj : Adj::LR }, Ins::Noop]).unwrap_err().index, 1);
+        let ins = Ins::RandomlyChoose { options : Box::new((Ins::Sub { adj : Adj::LR }, Ins::Noop,)) };
+        assert!(ScriptPrinter::print_ins(&ins).is_none());
+    }
+
+    #[test]
+    fn format_layout() {
+        let source = b"% Head\n\n\n>-!!  +v! % add\n%{ multi\n line %}\n\n:!#";
+        let output = ScriptParser::parse_all(source.iter().copied().map(Ok), Default::default());
+        assert_eq!(
+            ScriptPrinter::format(&output, source).unwrap(),
+            b"% Head\n\n>-\n+v! % add\n%{ multi\n line %}\n\n:!#\n"
+        );
+    }
+
+    #[test]
+    fn format_keeps_prose() {
+        let options = ParseOptions { unknown_chars : UnknownChars::Ignore };
+        let source  = b"Adds one + two\n>-  +v\n";
+        let output  = ScriptParser::parse_all(source.iter().copied().map(Ok), options);
+        assert_eq!(ScriptPrinter::format(&output, source).unwrap(), b"Adds one + two\n>-\n+v\n");
+    }
+
+    #[test]
+    fn format_keeps_bytes() {
+        let source = b"% \xFF\xFE  \n>-!!";
+        let output = ScriptParser::parse_all(source.iter().copied().map(Ok), Default::default());
+        assert_eq!(ScriptPrinter::format(&output, source).unwrap(), b"% \xFF\xFE\n>-\n");
+    }
+
+    #[test]
+    fn format_samples_idempotent() {
+        for entry in std::fs::read_dir("samples").unwrap() {
+            let source = std::fs::read(entry.unwrap().path()).unwrap();
+            let output = ScriptParser::parse_all(source.iter().copied().map(Ok), Default::default());
+            let once   = ScriptPrinter::format(&output, &source).unwrap();
+            let output = ScriptParser::parse_all(once.iter().copied().map(Ok), Default::default());
+            assert_eq!(ScriptPrinter::format(&output, &once).unwrap(), once);
+        }
+    }
+
+    #[test]
+    fn parse_print_parse_identity() {
+        const BASES : [&str; 6] = ["+", "*", "~", ">", ";", ":"];
+        const ADJS  : [&str; 5] = ["\\", "/", "-", "^", "v"];
+        const MODS  : [&str; 5] = ["!", "?", "!#", "?#", "#"];
+        let mut rng = StdRng::seed_from_u64(0x150_1A96);
+        for _ in 0..10_000 {
+            let mut src = String::new();
+            for _ in 0..rng.random_range(1..8) {
+                let base = BASES[rng.random_range(0..BASES.len())];
+                src.push_str(base);
+                if (base != ":") { src.push_str(ADJS[rng.random_range(0..ADJS.len())]); }
+                for _ in 0..rng.random_range(0..5) {
+                    src.push_str(MODS[rng.random_range(0..MODS.len())]);
+                }
+                src.push(' ');
+            }
+            // Some modifier combinations can not be inverted.
+            let Ok(script) = ScriptParser::parse(src.bytes().map(Ok))
+                else { continue; };
+            let printed = ScriptPrinter::print(&script).unwrap_or_else(|err| panic!("{err} in {src:?}"));
+            assert_eq!(parse(&printed), script, "{src:?} printed as {printed:?}");
+        }
+    }
+
+}
@@ -0,0 +1,296 @@
+//! Flat bytecode lowered from [`Ins`], which runs without walking nested instructions.
+
+
+use crate::runner::ins::Ins;
+use crate::runner::optimizer::{ self, Peephole };
+use crate::world::{ Coord, Dir, ArithOp };
+
+
+/// How a [`ScriptRunner`](crate::runner::ScriptRunner) runs instructions.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Engine {
+
+    /// Walks the tree of [`Ins`] each step.
+    #[default]
+    Tree,
+
+    /// Lowers the script to flat bytecode once, with the cells each instruction targets already resolved,
+    /// and runs that instead. Behaves exactly the same as [`Engine::Tree`].
+    ///
+    /// *Note: Each step still records history, so on short scripts this is not measurably faster than [`Engine::Tree`],
+    /// and can be slower. See the `engine` benchmark.*
+    Bytecode,
+
+    /// Like [`Engine::Bytecode`], but also merges runs of head moves into a single displacement.
+    ///
+    /// *Note: A merged run is a single step, so step counts, traces, and instruction breakpoints
+    /// inside a run differ from [`Engine::Tree`]. Everything else behaves the same.*
+    Optimized
+
+}
+
+
+/// A single bytecode operation.
+///
+/// Every [`Coord`] is an offset from the world head.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Op {
+
+    /// Moves the head by an offset.
+    Move(Coord),
+
+    /// Moves the head by an offset, multiplied by the cell at the world head.
+    MoveDynamic(Coord),
+
+    /// Applies an operation to two cells, and stores the result at the world head.
+    Arith {
+        /// The operation to apply.
+        op : ArithOp,
+        /// The left cell.
+        l  : Coord,
+        /// The right cell.
+        r  : Coord
+    },
+
+    /// Swaps two cells.
+    Swap {
+        /// The left cell.
+        l : Coord,
+        /// The right cell.
+        r : Coord
+    },
+
+    /// Skips the next `skip` ops unless the cell at the world head is not zero.
+    IfNotZero {
+        /// The number of ops to skip.
+        skip : usize
+    },
+
+    /// Skips the next `skip` ops unless the cell at the world head is zero.
+    IfZero {
+        /// The number of ops to skip.
+        skip : usize
+    },
+
+    /// Skips the next `skip` ops unless moving the head by some offset, with components between those of `low` and `high`,
+    /// takes it out of range.
+    IfOutOfRange {
+        /// The smallest components of the offset.
+        low  : Coord,
+        /// The largest components of the offset.
+        high : Coord,
+        /// The number of ops to skip.
+        skip : usize
+    },
+
+    /// Makes a random choice, skipping the next `skip` ops if the second option is chosen.
+    Choose {
+        /// The number of ops to skip.
+        skip : usize
+    },
+
+    /// Skips the next `skip` ops.
+    Skip {
+        /// The number of ops to skip.
+        skip : usize
+    },
+
+    /// Jumps through code by the cell at the world head.
+    ///
+    /// *Note: Inside a condition or random choice, the instruction then finishes with [`Op::Next`],
+    /// so the script head still moves on past where the jump landed.*
+    Jump(Dir),
+
+    /// Dumps the current world state, if enabled.
+    Dump,
+
+    /// Finishes the instruction, moving on to the next one.
+    Next,
+
+    /// Finishes the instruction, staying where a jump moved the script head.
+    Jumped,
+
+    /// Finishes the instruction, continuing at the instruction at an index.
+    Continue(usize)
+
+}
+
+
+/// A script lowered to bytecode.
+pub(super) struct Bytecode {
+
+    /// The ops of every instruction, one after another.
+    ops     : Vec<Op>,
+
+    /// The index of the first op of each instruction.
+    entries : Vec<usize>,
+
+    /// Whether runs of head moves were merged.
+    optimized : bool
+
+}
+
+impl Bytecode {
+
+    /// Lowers a script to bytecode, merging runs of head moves if `optimize` is set.
+    pub(super) fn lower(script : &[Ins], optimize : bool) -> Self {
+        let mut bytecode = Self { ops : Vec::new(), entries : Vec::with_capacity(script.len()), optimized : optimize };
+        let peepholes = if (optimize) { optimizer::optimize(script) } else { vec![Peephole::Keep; script.len()] };
+        for (ins, peephole,) in script.iter().zip(peepholes) {
+            bytecode.entries.push(bytecode.ops.len());
+            match (peephole) {
+                Peephole::Keep => {
+                    bytecode.lower_ins(ins);
+                    bytecode.ops.push(if let Ins::JumpThruCode { .. } = ins { Op::Jumped } else { Op::Next });
+                },
+                Peephole::Move { offset, low, high, next } => {
+                    // Moves one instruction at a time instead, where the run could fail part way through.
+                    let guard = bytecode.ops.len();
+                    bytecode.ops.push(Op::IfOutOfRange { low, high, skip : 0 });
+                    bytecode.lower_ins(ins);
+                    bytecode.ops.push(Op::Next);
+                    bytecode.ops[guard] = Op::IfOutOfRange { low, high, skip : bytecode.ops.len() - guard - 1 };
+                    if (offset != Coord::ZERO) { bytecode.ops.push(Op::Move(offset)); }
+                    bytecode.ops.push(Op::Continue(next));
+                }
+            }
+        }
+        bytecode
+    }
+
+    /// Get the engine which runs this bytecode.
+    pub(super) fn engine(&self) -> Engine {
+        if (self.optimized) { Engine::Optimized } else { Engine::Bytecode }
+    }
+
+    /// Appends the ops of a single instruction, without the final [`Op::Next`].
+    fn lower_ins(&mut self, ins : &Ins) { match (ins) {
+
+        Ins::MoveHeadOne { adj, dir } => { self.ops.push(Op::Move(Coord::from((*adj, *dir,)))); },
+
+        Ins::MoveHeadDynamic { adj, dir } => { self.ops.push(Op::MoveDynamic(Coord::from((*adj, *dir,)))); },
+
+        Ins::Add  { adj } => { self.lower_arith(ArithOp::Add, Coord::ZERO + *adj); },
+        Ins::Sub  { adj } => { self.lower_arith(ArithOp::Sub, Coord::ZERO + *adj); },
+        Ins::Mul  { adj } => { self.lower_arith(ArithOp::Mul, Coord::ZERO + *adj); },
+        Ins::SDiv { adj } => { self.lower_arith(ArithOp::Div, Coord::ZERO + *adj); },
+
+        Ins::Swap { adj } => {
+            let (l, r,) = Coord::ZERO + *adj;
+            self.ops.push(Op::Swap { l, r });
+        },
+
+        Ins::Noop => { },
+
+        Ins::JumpThruCode { dir } => { self.ops.push(Op::Jump(*dir)); },
+
+        Ins::IfNotZeroCond { ins } => { self.lower_guarded(|skip| Op::IfNotZero { skip }, ins); },
+
+        Ins::IfZeroCond { ins } => { self.lower_guarded(|skip| Op::IfZero { skip }, ins); },
+
+        Ins::RandomlyChoose { options } => {
+            let choose = self.ops.len();
+            self.ops.push(Op::Choose { skip : 0 });
+            self.lower_ins(&options.0);
+            let skip = self.ops.len();
+            self.ops.push(Op::Skip { skip : 0 });
+            self.ops[choose] = Op::Choose { skip : self.ops.len() - choose - 1 };
+            self.lower_ins(&options.1);
+            self.ops[skip] = Op::Skip { skip : self.ops.len() - skip - 1 };
+        },
+
+        Ins::DumpWorld => { self.ops.push(Op::Dump); }
+
+    } }
+
+    /// Appends the ops of an arithmetic instruction on the cells at the `l` and `r` offsets.
+    fn lower_arith(&mut self, op : ArithOp, (l, r,) : (Coord, Coord,)) {
+        self.ops.push(Op::Arith { op, l, r });
+    }
+
+    /// Appends a guard op, followed by the ops of `ins` which the guard can skip.
+    fn lower_guarded(&mut self, guard : impl Fn(usize) -> Op, ins : &Ins) {
+        let at = self.ops.len();
+        self.ops.push(guard(0));
+        self.lower_ins(ins);
+        self.ops[at] = guard(self.ops.len() - at - 1);
+    }
+
+    /// Get the ops of the instruction at `index`, and every instruction after it.
+    #[inline]
+    pub(super) fn ops_from(&self, index : usize) -> &[Op] {
+        &self.ops[self.entries[index]..]
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ScriptParser;
+    use crate::runner::{ ScriptRunner, ScriptedChoices, StepTrace };
+    use crate::world::cell::{ U8Cell, I32Cell };
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Runs a script with an engine, collecting the output and a description of each step.
+    fn run(src : &[u8], input : &str, engine : Engine) -> (String, Vec<String>,) {
+        let script = ScriptParser::parse(src.iter().copied().map(Ok)).unwrap();
+        let steps  = Rc::new(RefCell::new(Vec::new()));
+        let (mut runner, output,) = ScriptRunner::<I32Cell>::in_memory(script, input);
+        runner.set_engine(engine);
+        runner.set_choices(ScriptedChoices::new([true, false, false]));
+        runner.set_step_limit(Some(10_000));
+        runner.set_dump_output(None);
+        runner.set_tracer(Some(Box::new({
+            let steps = Rc::clone(&steps);
+            move |step : &StepTrace<'_, I32Cell>| steps.borrow_mut().push(format!("{:?} {:?} {} {} {:?}",
+                step.script_head, step.branches, step.head_before, step.head_after, step.writes
+            ))
+        })));
+        let result = runner.run();
+        steps.borrow_mut().push(format!("{result:?}"));
+        (String::from_utf8(output.take()).unwrap(), steps.take(),)
+    }
+
+    #[test]
+    fn engines_agree() {
+        let scripts : [(&[u8], &str,); 12] = [
+            (include_bytes!("../../samples/simple_hello_world.isolang"), "",),
+            (include_bytes!("../../samples/simple_save.isolang"), "",),
+            (include_bytes!("../../samples/echo_one.isolang"), "é",),
+            (include_bytes!("../../samples/echo.isolang"), "Hello, World!",),
+            (include_bytes!("../../samples/echo_forever.isolang"), "abc",),
+            (b">- >- >- +-? ~/# >-# ;-#? :! ;- >-!", "",),
+            (b">- >- +-! >- +-! *-! >-", "",),
+            (b">- ~- ~- +/? >-#? *\\ @ :", "ab",),
+            // Jumps inside a condition or random choice still move on past where they land.
+            (b">- >- +- :? >- >- >- >-", "",),
+            (b">- >- +- >-! :!? >- >- >-", "",),
+            (b">- >- +- :# >- >- >- >-", "",),
+            (b">- >- +- >-! :!# >- >- >-", "",)
+        ];
+        for (src, input,) in scripts {
+            let tree = run(src, input, Engine::Tree);
+            assert_eq!(tree, run(src, input, Engine::Bytecode));
+            assert_eq!(tree.0, run(src, input, Engine::Optimized).0);
+        }
+    }
+
+    #[test]
+    fn lower_nested() {
+        let script = ScriptParser::parse(b">-?# :!".iter().copied().map(Ok)).unwrap();
+        let bytecode = Bytecode::lower(&script, false);
+        assert_eq!(bytecode.entries, [0, 6]);
+        assert!(matches!(bytecode.ops[..], [
+            Op::Choose { skip : 2 }, Op::Move(_), Op::Skip { skip : 2 }, Op::IfNotZero { skip : 1 }, Op::Move(_), Op::Next,
+            Op::Jump(Dir::L), Op::Jumped
+        ]));
+        // Bytecode also runs on the smallest cells.
+        let (mut runner, _,) = ScriptRunner::<U8Cell>::in_memory(script, "");
+        runner.set_engine(Engine::Bytecode);
+        assert!(runner.run_for(10).is_ok());
+    }
+
+}
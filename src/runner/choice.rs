@@ -0,0 +1,53 @@
+//! Sources of the random choices made by [`Ins::RandomlyChoose`](crate::runner::ins::Ins::RandomlyChoose).
+
+
+use rand::{ Rng, RngCore };
+use std::collections::VecDeque;
+
+
+/// A source of the random choices made by [`Ins::RandomlyChoose`](crate::runner::ins::Ins::RandomlyChoose).
+///
+/// This is implemented for every [`RngCore`], and for [`ScriptedChoices`].
+pub trait ChoiceSource {
+
+    /// Makes a choice.
+    ///
+    /// ### Returns
+    /// Returns `true` if the first option should be run, or `false` if the second should be.
+    fn choose(&mut self) -> bool;
+
+}
+
+impl<R : RngCore> ChoiceSource for R {
+    fn choose(&mut self) -> bool { self.random() }
+}
+
+
+/// A fixed sequence of choices, which repeats once it runs out.
+#[derive(Clone, Debug)]
+pub struct ScriptedChoices {
+
+    /// The choices which have not been made yet in this repetition.
+    choices : VecDeque<bool>
+
+}
+
+impl ScriptedChoices {
+
+    /// Creates a source which makes `choices` in order, repeating them once it runs out.
+    ///
+    /// *Note: An empty sequence always chooses the first option.*
+    pub fn new(choices : impl IntoIterator<Item = bool>) -> Self {
+        Self { choices : choices.into_iter().collect() }
+    }
+
+}
+
+impl ChoiceSource for ScriptedChoices {
+    fn choose(&mut self) -> bool {
+        let Some(choice) = self.choices.pop_front()
+            else { return true; };
+        self.choices.push_back(choice);
+        choice
+    }
+}
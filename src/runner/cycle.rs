@@ -0,0 +1,159 @@
+//! Detection of runs which repeat the same states forever.
+
+
+use crate::world::{ World, Cell, Coord };
+use std::collections::BTreeSet;
+use std::hash::{ DefaultHasher, Hash, Hasher };
+use core::fmt;
+
+
+/// A sequence of steps which returns to the state it started from, without any input, output or random choices,
+/// and so repeats forever.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Cycle {
+
+    /// The number of steps in the cycle.
+    pub length       : u64,
+
+    /// The index of the instruction run by each step of the cycle, in order.
+    pub instructions : Vec<usize>
+
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the same state repeats every {} step(s) without any input or output, running instruction(s) ", self.length)?;
+        let instructions = self.instructions.iter().copied().collect::<BTreeSet<_>>();
+        let mut instructions = instructions.into_iter().peekable();
+        let mut first = true;
+        while let Some(start) = instructions.next() {
+            let mut end = start;
+            while let Some(next) = instructions.next_if_eq(&(end + 1)) { end = next; }
+            if (! first) { write!(f, ", ")?; }
+            first = false;
+            if (start == end) { write!(f, "{start}")?; }
+            else { write!(f, "{start}-{end}")?; }
+        }
+        Ok(())
+    }
+}
+
+
+/// The full state of a runner, saved to be compared against.
+struct Snapshot<C : Cell> {
+
+    /// The hash of the state.
+    hash        : u64,
+
+    /// The index of the next instruction to run.
+    script_head : usize,
+
+    /// The world head.
+    world_head  : Coord,
+
+    /// Every cell which does not hold [`C::ONE`](Cell::ONE), sorted by [`Coord`].
+    cells       : Vec<(Coord, C,)>
+
+}
+
+
+/// Finds states which repeat, using Brent's algorithm over hashes of the full state.
+///
+/// A state is saved at steps which are further and further apart, and each step is compared against it.
+/// Any input, output or random choice forgets the saved state, as a cycle must not contain any.
+pub(super) struct CycleDetector<C : Cell> {
+
+    /// The saved state, if any.
+    saved     : Option<Snapshot<C>>,
+
+    /// The number of steps to run after saving a state, before saving the next one.
+    power     : u64,
+
+    /// The index of the instruction run by each step since the state was saved.
+    heads     : Vec<usize>,
+
+    /// The number of inputs, outputs and random choices when the state was saved.
+    io        : u64,
+
+    /// The combined hash of every cell which does not hold [`C::ONE`](Cell::ONE).
+    cell_hash : u64
+
+}
+
+impl<C : Cell> CycleDetector<C> {
+
+    /// Creates a detector with no saved state.
+    pub(super) fn new() -> Self {
+        Self { saved : None, power : 1, heads : Vec::new(), io : 0, cell_hash : 0 }
+    }
+
+    /// Forgets the saved state, after the state was changed other than by running a step.
+    ///
+    /// *Note: The hash of the cells is recalculated whenever a state is saved,
+    /// so changes to the world which were not seen by [`Self::write`] are only missed until then.*
+    pub(super) fn reset(&mut self) {
+        self.saved = None;
+        self.power = 1;
+        self.heads.clear();
+    }
+
+    /// Updates the hash of the cells, for a cell which is overwritten.
+    pub(super) fn write(&mut self, coord : Coord, old : &C, new : &C) {
+        if (coord == Coord::ZERO) { return; }
+        if (*old != C::ONE) { self.cell_hash ^= cell_hash(coord, old); }
+        if (*new != C::ONE) { self.cell_hash ^= cell_hash(coord, new); }
+    }
+
+    /// Checks the state after a step which ran the instruction at `ran`.
+    ///
+    /// `io` is the number of inputs, outputs and random choices made so far.
+    ///
+    /// ### Returns
+    /// Returns the cycle, if the state is the same as the saved state.
+    pub(super) fn step(&mut self, ran : usize, script_head : usize, world : &World<C>, io : u64) -> Option<Cycle> {
+        if (io != self.io) {
+            self.io = io;
+            self.reset();
+        }
+
+        self.heads.push(ran);
+        if let Some(saved) = &self.saved
+            && saved.hash == self.hash(script_head, world)
+            && saved.script_head == script_head
+            && saved.world_head == world.head()
+            && saved.cells.iter().map(|(coord, cell,)| (*coord, cell,)).eq(world.cells())
+        {
+            return Some(Cycle { length : self.heads.len() as u64, instructions : self.heads.clone() });
+        }
+
+        if (self.saved.is_none() || self.heads.len() as u64 >= self.power) {
+            if (self.saved.is_some()) { self.power *= 2; }
+            self.cell_hash = world.cells().into_iter().fold(0, |hash, (coord, cell,)| hash ^ cell_hash(coord, cell));
+            self.saved = Some(Snapshot {
+                hash       : self.hash(script_head, world),
+                script_head,
+                world_head : world.head(),
+                cells      : world.cells().into_iter().map(|(coord, cell,)| (coord, cell.clone(),)).collect()
+            });
+            self.heads.clear();
+        }
+        None
+    }
+
+    /// Hashes the full state.
+    fn hash(&self, script_head : usize, world : &World<C>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (script_head, world.head(), self.io, self.cell_hash,).hash(&mut hasher);
+        hasher.finish()
+    }
+
+}
+
+/// Hashes a single cell.
+///
+/// *Note: Cells too large for an [`isize`] may hash the same, which is caught when the full states are compared.*
+fn cell_hash<C : Cell>(coord : Coord, cell : &C) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (coord, cell.get_isize_val(),).hash(&mut hasher);
+    hasher.finish()
+}
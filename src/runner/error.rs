@@ -0,0 +1,125 @@
+//! Errors raised while running a script.
+
+
+use crate::runner::Cycle;
+use crate::world::{ Coord, ArithError, CellReadError };
+use core::fmt;
+use std::error::Error;
+use std::io;
+use std::str::Utf8Error;
+use std::time::Duration;
+
+
+/// An error raised while running an Isolang script.
+#[derive(Debug)]
+pub struct RuntimeError {
+
+    /// What went wrong.
+    pub kind        : RuntimeErrorKind,
+
+    /// The index of the instruction which was running.
+    pub script_head : usize,
+
+    /// The position of the world head when the error was raised.
+    pub world_head  : Coord
+
+}
+
+/// What went wrong while running an Isolang script.
+#[derive(Debug)]
+pub enum RuntimeErrorKind {
+
+    /// A cell was divided by zero.
+    DivisionByZero,
+
+    /// The result of an arithmetic instruction does not fit in a cell.
+    Overflow,
+
+    /// The world head was moved further than [`Coord::MAX_DISTANCE`] from the origin.
+    HeadOverflow,
+
+    /// Reading from the input stream or writing to the output stream failed.
+    Io(io::Error),
+
+    /// An invalid UTF-8 sequence was read from the input stream.
+    InvalidUtf8(Utf8Error),
+
+    /// The input stream was read after its end.
+    UnexpectedEof,
+
+    /// The script ran more steps than it was allowed to.
+    StepLimit {
+        /// The number of steps that were allowed.
+        steps : u64
+    },
+
+    /// The script ran for longer than it was allowed to.
+    Timeout {
+        /// The time that was allowed.
+        timeout : Duration
+    },
+
+    /// The script returned to a state it was already in, and so would run forever.
+    Cycle(Cycle)
+
+}
+
+impl RuntimeErrorKind {
+
+    /// Returns `true` if this error was raised because the script ran out of steps or time,
+    /// rather than because of anything it did.
+    pub fn is_limit(&self) -> bool {
+        matches!(self, Self::StepLimit { .. } | Self::Timeout { .. })
+    }
+
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at instruction {}, with world head at {}", self.kind, self.script_head, self.world_head)
+    }
+}
+
+impl fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::DivisionByZero      => write!(f, "division by zero"),
+        Self::Overflow            => write!(f, "arithmetic overflow"),
+        Self::HeadOverflow        => write!(f, "world head moved out of range"),
+        Self::Io(err)             => write!(f, "IO error: {err}"),
+        Self::InvalidUtf8(err)    => write!(f, "invalid UTF-8 input: {err}"),
+        Self::UnexpectedEof       => write!(f, "unexpected end of input"),
+        Self::StepLimit { steps } => write!(f, "step limit of {steps} reached"),
+        Self::Timeout { timeout } => write!(f, "timeout of {timeout:?} reached"),
+        Self::Cycle(cycle)        => write!(f, "infinite loop: {cycle}")
+    } }
+}
+
+impl Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> { match (&self.kind) {
+        RuntimeErrorKind::Io(err)          => Some(err),
+        RuntimeErrorKind::InvalidUtf8(err) => Some(err),
+        _                                  => None
+    } }
+}
+
+/// Allows using the `?` operator on `Err(ArithError)` types to auto-convert them to [`RuntimeErrorKind`].
+impl From<ArithError> for RuntimeErrorKind {
+    fn from(err : ArithError) -> Self { match (err) {
+        ArithError::DivisionByZero => Self::DivisionByZero,
+        ArithError::Overflow       => Self::Overflow
+    } }
+}
+
+/// Allows using the `?` operator on `Err(CellReadError)` types to auto-convert them to [`RuntimeErrorKind`].
+impl From<CellReadError> for RuntimeErrorKind {
+    fn from(err : CellReadError) -> Self { match (err) {
+        CellReadError::Io(err)   => Self::Io(err),
+        CellReadError::Utf8(err) => Self::InvalidUtf8(err),
+        CellReadError::Eof       => Self::UnexpectedEof
+    } }
+}
+
+/// Allows using the `?` operator on `Err(io::Error)` types to auto-convert them to [`RuntimeErrorKind`].
+impl From<io::Error> for RuntimeErrorKind {
+    fn from(err : io::Error) -> Self { Self::Io(err) }
+}
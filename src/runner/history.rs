@@ -0,0 +1,109 @@
+//! Undo log of the steps run by a [`ScriptRunner`](crate::runner::ScriptRunner).
+
+
+use crate::world::{ Cell, Coord };
+use std::collections::VecDeque;
+
+
+/// Everything needed to undo a single step.
+pub(super) struct UndoStep<C : Cell> {
+
+    /// The script head before the step.
+    pub(super) script_head : usize,
+
+    /// The world head before the step.
+    pub(super) world_head  : Coord,
+
+    /// The cells written during the step, with the values they held before, in order.
+    pub(super) writes      : Vec<(Coord, C,)>,
+
+    /// The cells read from the input stream during the step, in order.
+    pub(super) reads       : Vec<C>,
+
+    /// The random choices made during the step, in order.
+    pub(super) choices     : Vec<bool>
+
+}
+
+impl<C : Cell> UndoStep<C> {
+
+    /// Returns `true` if this step wrote to `coord`.
+    pub(super) fn wrote(&self, coord : Coord) -> bool {
+        self.writes.iter().any(|(written, _,)| *written == coord)
+    }
+
+}
+
+
+/// A bounded log of the most recent steps, which can be undone.
+pub(super) struct History<C : Cell> {
+
+    /// The maximum number of steps kept. Zero disables recording.
+    limit   : usize,
+
+    /// The recorded steps, oldest first.
+    steps   : VecDeque<UndoStep<C>>,
+
+    /// The step currently being run, if recording.
+    current : Option<UndoStep<C>>
+
+}
+
+impl<C : Cell> History<C> {
+
+    /// Creates a history which keeps at most `limit` steps.
+    pub(super) fn new(limit : usize) -> Self { Self {
+        limit,
+        steps   : VecDeque::new(),
+        current : None
+    } }
+
+    /// Get the maximum number of steps kept.
+    pub(super) fn limit(&self) -> usize { self.limit }
+
+    /// Sets the maximum number of steps kept, forgetting the oldest steps if there are too many.
+    pub(super) fn set_limit(&mut self, limit : usize) {
+        self.limit = limit;
+        while (self.steps.len() > limit) { self.steps.pop_front(); }
+    }
+
+    /// Get the number of steps which can be undone.
+    pub(super) fn len(&self) -> usize { self.steps.len() }
+
+    /// Get the most recent step.
+    pub(super) fn last(&self) -> Option<&UndoStep<C>> { self.steps.back() }
+
+    /// Starts recording a step, if recording is enabled.
+    pub(super) fn begin(&mut self, script_head : usize, world_head : Coord) {
+        if (self.limit > 0) {
+            self.current = Some(UndoStep { script_head, world_head, writes : Vec::new(), reads : Vec::new(), choices : Vec::new() });
+        }
+    }
+
+    /// Finishes recording a step, forgetting the oldest step if the limit is reached.
+    pub(super) fn end(&mut self) {
+        if let Some(step) = self.current.take() {
+            if (self.steps.len() >= self.limit) { self.steps.pop_front(); }
+            self.steps.push_back(step);
+        }
+    }
+
+    /// Takes the most recent step, to be undone.
+    pub(super) fn pop(&mut self) -> Option<UndoStep<C>> { self.steps.pop_back() }
+
+    /// Records a cell write in the current step, with the value the cell held before.
+    pub(super) fn write(&mut self, coord : Coord, old : impl FnOnce() -> C) {
+        if let Some(step) = &mut self.current { step.writes.push((coord, old(),)); }
+    }
+
+    /// Records a cell read from the input stream in the current step.
+    pub(super) fn read(&mut self, cell : &C) {
+        if let Some(step) = &mut self.current { step.reads.push(cell.clone()); }
+    }
+
+    /// Records a random choice in the current step.
+    pub(super) fn choice(&mut self, choice : bool) {
+        if let Some(step) = &mut self.current { step.choices.push(choice); }
+    }
+
+}
@@ -45,6 +45,7 @@ pub enum Ins {
 
     /// Moves the head left or right some number of cells on an adj.
     /// The distance depends on the value in the cell at the world head.
+    /// A negative value moves the head in the opposite direction.
     MoveHeadDynamic { 
         /// The adj to move along.
         adj : Adj,
@@ -70,7 +71,8 @@ pub enum Ins {
         adj : Adj
     },
 
-    /// Signed integer divides the two cells targeted by the adj (left divided by right) and stores it at the world head.
+    /// Integer divides the two cells targeted by the adj (left divided by right) and stores it at the world head.
+    /// The division is signed for signed cell types.
     SDiv {
         /// The adj on which the two cells fall.
         adj : Adj
@@ -86,6 +88,9 @@ pub enum Ins {
     Noop,
 
     /// Jumps through code a number of instructions equal to the current cell.
+    /// A negative value jumps in the opposite direction.
+    ///
+    /// *Note: Inside a condition or random choice, the script head then moves on by one, like after any other instruction.*
     JumpThruCode {
         /// R is forward in code, L is backward in code
         dir : Dir
@@ -109,8 +114,7 @@ pub enum Ins {
         options : Box<(Ins, Ins)>
     },
 
-    #[cfg(debug_assertions)]
-    /// Dumps the current world state to the console.
+    /// Dumps the current world state, if enabled.
     DumpWorld
 
 }
@@ -144,7 +148,6 @@ impl Ins {
 
         Self::RandomlyChoose { .. } => Err(BadInvertError),
 
-        #[cfg(debug_assertions)]
         Self::DumpWorld => Err(BadInvertError)
 
     } }
@@ -164,6 +167,13 @@ impl Ins {
         })
     }
 
+    /// Returns `true` if running this instruction may make a random choice.
+    pub fn is_random(&self) -> bool { match (self) {
+        Self::RandomlyChoose { .. } => true,
+        Self::IfNotZeroCond { ins } | Self::IfZeroCond { ins } => ins.is_random(),
+        _ => false
+    } }
+
 }
 
 
@@ -1,22 +1,65 @@
 //! Isolang script runner.
 
 
-use crate::world::{ World, Cell, Adj, Coord, Dir };
-use rand::random;
+use crate::world::{ World, Cell, Adj, Coord, Dir, ArithOp, ArithMode, EofPolicy, MemoryOutput, CellReadError };
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::io::{ self, Read, Write };
+use std::time::{ Duration, Instant };
 
 
 pub mod ins;
 use ins::Ins;
 
+mod error;
+pub use error::{ RuntimeError, RuntimeErrorKind };
+
+mod choice;
+pub use choice::{ ChoiceSource, ScriptedChoices };
+
+mod trace;
+pub use trace::{ StepTrace, Branch, CellWrite, Tracer, TraceFormat, TraceWriter };
+
+mod history;
+use history::{ History, UndoStep };
+
+mod cycle;
+pub use cycle::Cycle;
+use cycle::CycleDetector;
+
+mod optimizer;
+
+mod bytecode;
+pub use bytecode::Engine;
+use bytecode::{ Bytecode, Op };
+
 
 /// Isolang script runner.
 pub struct ScriptRunner<C : Cell> {
 
     /// The instructions in the script.
-    script : Vec<Ins>,
+    script     : Vec<Ins>,
 
     /// The current running state.
-    state  : ScriptRunnerState<C>
+    state      : ScriptRunnerState<C>,
+
+    /// The number of steps run so far.
+    steps      : u64,
+
+    /// The maximum number of steps to run, if any.
+    step_limit : Option<u64>,
+
+    /// The maximum time to run for, and when it started, if any.
+    time_limit : Option<(Instant, Duration,)>,
+
+    /// Whether the script was halted by reading past the end of input.
+    halted     : bool,
+
+    /// Receives a trace of each step run, if any.
+    tracer     : Option<Box<dyn Tracer<C>>>,
+
+    /// The script lowered to bytecode, if run by [`Engine::Bytecode`] or [`Engine::Optimized`].
+    bytecode   : Option<Bytecode>
 
 }
 
@@ -24,22 +67,92 @@ pub struct ScriptRunner<C : Cell> {
 struct ScriptRunnerState<C : Cell> {
 
     /// The script running head.
-    script_head : usize,
+    script_head  : usize,
 
     /// The world to run on.
-    world       : World<C>
+    world        : World<C>,
+
+    /// What happens when the result of an arithmetic instruction does not fit in a cell.
+    arith_mode   : ArithMode,
+
+    /// The source of choices for [`Ins::RandomlyChoose`].
+    choices      : Box<dyn ChoiceSource>,
+
+    /// Whether branches and writes are being recorded for a [`Tracer`].
+    recording    : bool,
+
+    /// The branches taken during the current step, if recording.
+    branches     : Vec<Branch>,
+
+    /// The cells written during the current step, if recording.
+    writes       : Vec<CellWrite<C>>,
+
+    /// The steps which can be undone.
+    history      : History<C>,
+
+    /// The stream [`Ins::DumpWorld`] writes to, if enabled.
+    dump         : Option<Box<dyn Write>>,
+
+    /// Random choices which were undone, to be made again before asking [`Self::choices`], last first.
+    unchosen     : Vec<bool>,
+
+    /// The number of cells read from the input stream or written to the output stream,
+    /// and random choices made, so far.
+    interactions : u64,
+
+    /// Finds states which repeat, if enabled.
+    cycles       : Option<CycleDetector<C>>
 
 }
 
 impl<C : Cell> ScriptRunner<C> {
 
-    /// Construct a new runner from a [`Vec`] of instructions.
-    pub fn new(script : Vec<Ins>) -> Self { Self {
+    /// Construct a new runner from a [`Vec`] of instructions, which reads from stdin and writes to stdout.
+    pub fn new(script : Vec<Ins>) -> Self {
+        Self::with_world(script, World::default())
+    }
+
+    /// Construct a new runner from a [`Vec`] of instructions, which reads from `input` and writes to `output`.
+    pub fn with_io<R, W>(script : Vec<Ins>, input : R, output : W) -> Self
+    where
+        R : Read + 'static,
+        W : Write + 'static
+    {
+        Self::with_world(script, World::with_io(input, output))
+    }
+
+    /// Construct a new runner from a [`Vec`] of instructions, which reads from `input` and writes to memory.
+    ///
+    /// ### Returns
+    /// Returns the runner, and a handle to the output it writes.
+    pub fn in_memory(script : Vec<Ins>, input : impl Into<Vec<u8>>) -> (Self, MemoryOutput,) {
+        let output = MemoryOutput::default();
+        (Self::with_io(script, io::Cursor::new(input.into()), output.clone()), output,)
+    }
+
+    /// Construct a new runner from a [`Vec`] of instructions, which runs on `world`.
+    pub fn with_world(script : Vec<Ins>, world : World<C>) -> Self { Self {
         script,
-        state : ScriptRunnerState {
-            script_head : 0,
-            world       : World::default()
-        }
+        state      : ScriptRunnerState {
+            script_head  : 0,
+            world,
+            arith_mode   : ArithMode::default(),
+            choices      : Box::new(rand::rng()),
+            recording    : false,
+            branches     : Vec::new(),
+            writes       : Vec::new(),
+            history      : History::new(0),
+            dump         : Some(Box::new(io::stdout())),
+            unchosen     : Vec::new(),
+            interactions : 0,
+            cycles       : None
+        },
+        steps      : 0,
+        step_limit : None,
+        time_limit : None,
+        halted     : false,
+        tracer     : None,
+        bytecode   : None
     } }
 
     /// Get a mutable reference to the [`World`] in this runner.
@@ -48,108 +161,702 @@ impl<C : Cell> ScriptRunner<C> {
     /// Get a non-mutable reference to the [`World`] in this runner. 
     pub fn world(&self) -> &World<C> { &self.state.world }
 
+    /// Sets what happens when the result of an arithmetic instruction does not fit in a cell.
+    pub fn set_arith_mode(&mut self, arith_mode : ArithMode) { self.state.arith_mode = arith_mode; }
+
+    /// Sets the source of choices for [`Ins::RandomlyChoose`], which is the thread RNG by default.
+    pub fn set_choices(&mut self, choices : impl ChoiceSource + 'static) { self.state.choices = Box::new(choices); }
+
+    /// Makes [`Ins::RandomlyChoose`] choose using an RNG seeded with `seed`, so that runs can be reproduced.
+    pub fn set_seed(&mut self, seed : u64) { self.set_choices(StdRng::seed_from_u64(seed)); }
+
+    /// Sets the maximum number of steps to run, after which [`RuntimeErrorKind::StepLimit`] is raised.
+    pub fn set_step_limit(&mut self, step_limit : Option<u64>) { self.step_limit = step_limit; }
+
+    /// Sets the maximum time to run for, starting now, after which [`RuntimeErrorKind::Timeout`] is raised.
+    ///
+    /// *Note: The clock is only checked every [`Self::TIMEOUT_CHECK_INTERVAL`] steps.*
+    pub fn set_timeout(&mut self, timeout : Option<Duration>) {
+        self.time_limit = timeout.map(|timeout| (Instant::now(), timeout,));
+    }
+
+    /// Sets a tracer which receives a [`StepTrace`] after each successful step.
+    pub fn set_tracer(&mut self, tracer : Option<Box<dyn Tracer<C>>>) {
+        self.state.recording = tracer.is_some();
+        self.tracer          = tracer;
+    }
+
+    /// Sets the stream [`Ins::DumpWorld`] writes to, which is stdout by default.
+    ///
+    /// If `None`, [`Ins::DumpWorld`] does nothing.
+    pub fn set_dump_output(&mut self, dump : Option<Box<dyn Write>>) { self.state.dump = dump; }
+
+    /// Sets how many of the most recent steps are recorded, so that they can be undone with [`Self::step_back`].
+    ///
+    /// Zero, the default, disables recording.
+    pub fn set_history_limit(&mut self, limit : usize) { self.state.history.set_limit(limit); }
+
+    /// Get how many of the most recent steps are recorded.
+    pub fn history_limit(&self) -> usize { self.state.history.limit() }
+
+    /// Get the number of steps which can currently be undone.
+    pub fn history_len(&self) -> usize { self.state.history.len() }
+
+    /// Sets whether [`RuntimeErrorKind::Cycle`] is raised when the script returns to a state it was already in,
+    /// without any input, output or random choices since, and so would run forever.
+    ///
+    /// *Note: Each step hashes the state, and the full state is compared when the hashes match,
+    /// which makes running slower.*
+    pub fn set_cycle_detection(&mut self, enabled : bool) {
+        self.state.cycles = enabled.then(CycleDetector::new);
+    }
+
+    /// Returns `true` if cycles are detected.
+    pub fn cycle_detection(&self) -> bool { self.state.cycles.is_some() }
+
+    /// Sets how instructions are run, which is [`Engine::Tree`] by default.
+    ///
+    /// [`Engine::Bytecode`] and [`Engine::Optimized`] lower the whole script when set.
+    pub fn set_engine(&mut self, engine : Engine) {
+        self.bytecode = match (engine) {
+            Engine::Tree      => None,
+            Engine::Bytecode  => Some(Bytecode::lower(&self.script, false)),
+            Engine::Optimized => Some(Bytecode::lower(&self.script, true))
+        };
+    }
+
+    /// Get how instructions are run.
+    pub fn engine(&self) -> Engine {
+        self.bytecode.as_ref().map_or(Engine::Tree, Bytecode::engine)
+    }
+
+    /// Get the instructions in the script.
+    pub fn script(&self) -> &[Ins] { &self.script }
+
+    /// Get the index of the next instruction to run.
+    pub fn script_head(&self) -> usize { self.state.script_head }
+
+    /// Get the number of steps run so far.
+    pub fn steps(&self) -> u64 { self.steps }
+
 }
 
 
 impl<C : Cell> ScriptRunner<C> {
 
+    /// The number of steps between checks of the clock, when a timeout is set.
+    pub const TIMEOUT_CHECK_INTERVAL : u64 = 1024;
+
+    /// Runs the script until it finishes.
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        while (self.run_next()?) { }
+        Ok(())
+    }
+
+    /// Runs at most `steps` steps of the script.
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Ok(true)` if the program has finished.
+    /// - `Ok(false)` if `steps` steps were run and the program has not finished.
+    /// - `Err(_)` if a step failed.
+    pub fn run_for(&mut self, steps : u64) -> Result<bool, RuntimeError> {
+        for _ in 0..steps {
+            if (! self.run_next()?) { return Ok(true); }
+        }
+        Ok(self.is_finished())
+    }
+
+    /// Returns `true` if the program has finished, and [`Self::run_next`] would not run any more steps.
+    pub fn is_finished(&self) -> bool {
+        self.halted || self.state.script_head >= self.script.len()
+    }
+
     /// Runs the next step in the script.
     ///
     /// ### Returns
-    /// Returns `false` if the program has finished.
-    pub fn run_next(&mut self) -> bool {
+    /// Returns:
+    /// - `Ok(true)` if a step was run.
+    /// - `Ok(false)` if the program has finished.
+    /// - `Err(_)` if the step failed.
+    pub fn run_next(&mut self) -> Result<bool, RuntimeError> {
+        if (self.halted) { return Ok(false); }
         let Some(ins) = self.script.get(self.state.script_head)
-            else { return false; };
-        if (self.state.run_ins(ins)) {
-            self.state.script_head += 1;
+            else { return Ok(false); };
+        if let Some(steps) = self.step_limit && self.steps >= steps {
+            return Err(self.state.error(RuntimeErrorKind::StepLimit { steps }));
+        }
+        if let Some((start, timeout,)) = self.time_limit
+            && self.steps.is_multiple_of(Self::TIMEOUT_CHECK_INTERVAL)
+            && start.elapsed() >= timeout
+        {
+            return Err(self.state.error(RuntimeErrorKind::Timeout { timeout }));
         }
+        self.steps += 1;
+        let script_head = self.state.script_head;
+        let head_before = self.state.world.head();
+        self.state.branches.clear();
+        self.state.writes.clear();
+        self.state.history.begin(script_head, head_before);
+        let result = match (&self.bytecode) {
+            Some(bytecode) => self.state.run_ops(bytecode.ops_from(script_head)),
+            None           => self.state.run_ins(ins)
+        };
+        self.state.history.end();
+        match (result) {
+            Ok(true)  => { self.state.script_head += 1; },
+            Ok(false) => { },
+            Err(RuntimeErrorKind::UnexpectedEof) if (self.state.world.eof_policy() == EofPolicy::Halt) => {
+                self.halted = true;
+                return Ok(false);
+            },
+            Err(kind) => { return Err(self.state.error(kind)); }
+        }
+        if let Some(tracer) = &mut self.tracer {
+            tracer.trace(&StepTrace {
+                step        : self.steps,
+                script_head,
+                ins,
+                branches    : &self.state.branches,
+                head_before,
+                head_after  : self.state.world.head(),
+                writes      : &self.state.writes
+            });
+        }
+        if let Some(cycles) = &mut self.state.cycles
+            && let Some(cycle) = cycles.step(script_head, self.state.script_head, &self.state.world, self.state.interactions)
+        {
+            return Err(self.state.error(RuntimeErrorKind::Cycle(cycle)));
+        }
+        Ok(true)
+    }
+}
+
+impl<C : Cell> ScriptRunner<C> {
+
+    /// Undoes the most recent recorded step.
+    ///
+    /// Cells read from the input stream and random choices made during the step are given back,
+    /// so that running the step again behaves the same.
+    ///
+    /// *Note: Anything written to the output stream is not taken back.*
+    ///
+    /// ### Returns
+    /// Returns `false` if there were no recorded steps to undo.
+    pub fn step_back(&mut self) -> bool {
+        let Some(step) = self.state.history.pop()
+            else { return false; };
+        self.state.undo(step);
+        self.steps  -= 1;
+        self.halted  = false;
         true
     }
+
+    /// Undoes recorded steps until the most recent step which wrote to `coord` has been undone,
+    /// so that it is the next step to run.
+    ///
+    /// ### Returns
+    /// Returns `false` if no recorded step wrote to `coord`, in which case every recorded step was undone.
+    pub fn step_back_to_write(&mut self, coord : Coord) -> bool {
+        loop {
+            let wrote = self.state.history.last().is_some_and(|step| step.wrote(coord));
+            if (! self.step_back()) { return false; }
+            if (wrote) { return true; }
+        }
+    }
+
 }
 
 impl<C : Cell> ScriptRunnerState<C> {
 
+    /// Creates a [`RuntimeError`] at the current script and world heads.
+    fn error(&self, kind : RuntimeErrorKind) -> RuntimeError {
+        RuntimeError {
+            kind,
+            script_head : self.script_head,
+            world_head  : self.world.head()
+        }
+    }
 
 
-    /// Calls the function `f` on the two cells currently targeted by `adj`.
-    fn run_binop<F>(&mut self, adj : Adj, f : F)
-    where
-        F : FnOnce(C, C) -> C
-    {
+    /// Reverts everything done by a recorded step.
+    fn undo(&mut self, step : UndoStep<C>) {
+        self.script_head          = step.script_head;
+        *self.world.head_mut()    = step.world_head;
+        for (coord, old,) in step.writes.into_iter().rev() {
+            self.world.poke(coord, old);
+        }
+        for cell in step.reads.into_iter().rev() {
+            self.world.unread(cell);
+        }
+        self.unchosen.extend(step.choices.into_iter().rev());
+        if let Some(cycles) = &mut self.cycles { cycles.reset(); }
+    }
+
+
+    /// Reads a cell, recording it if it came from the input stream.
+    fn read(&mut self, coord : Coord) -> Result<C, CellReadError> {
+        let reads_input = self.world.reads_input(coord);
+        let cell        = self.world.get(coord)?;
+        if (reads_input) {
+            self.history.read(&cell);
+            self.interactions += 1;
+        }
+        Ok(cell)
+    }
+
+    /// Makes a random choice for [`Ins::RandomlyChoose`], recording it.
+    fn choose(&mut self) -> bool {
+        let choice = self.unchosen.pop().unwrap_or_else(|| self.choices.choose());
+        self.history.choice(choice);
+        self.interactions += 1;
+        choice
+    }
+
+
+    /// Applies the operation `op` to the two cells currently targeted by `adj`,
+    /// and stores the result at the world head.
+    fn run_binop(&mut self, adj : Adj, op : ArithOp) -> Result<(), RuntimeErrorKind> {
+        let (l, r,) = self.world.head() + adj;
+        self.run_arith(op, l, r)
+    }
+
+    /// Applies the operation `op` to the cells at `l` and `r`, and stores the result at the world head.
+    fn run_arith(&mut self, op : ArithOp, l : Coord, r : Coord) -> Result<(), RuntimeErrorKind> {
         let head = self.world.head();
-        let (l, r,) = head + adj;
-        let l = self.world.get(l);
-        let r = self.world.get(r);
-        self.world.insert(head, f(l, r));
+        let l = self.read(l)?;
+        let r = self.read(r)?;
+        self.write(head, l.arith(op, r, self.arith_mode)?)?;
+        Ok(())
+    }
+
+    /// Swaps the cells at `l` and `r`.
+    fn run_swap(&mut self, l : Coord, r : Coord) -> Result<(), RuntimeErrorKind> {
+        let lv = self.read(l)?;
+        let rv = self.read(r)?;
+        self.write(l, rv)?;
+        self.write(r, lv)?;
+        Ok(())
+    }
+
+    /// Moves the script head through code by the cell at the world head.
+    fn run_jump(&mut self, dir : Dir) -> Result<(), RuntimeErrorKind> {
+        let cell_val = self.read(self.world.head())?.get_isize_val();
+        let cell_val = match (dir) {
+            Dir::L => cell_val.saturating_neg(),
+            Dir::R => cell_val
+        };
+        self.script_head = self.script_head.saturating_add_signed(cell_val);
+        Ok(())
+    }
+
+    /// Moves the world head by `offset`, or `None` if the offset itself overflowed.
+    fn move_head(&mut self, offset : Option<Coord>) -> Result<(), RuntimeErrorKind> {
+        let head = offset.and_then(|offset| self.world.head().checked_add(offset))
+            .filter(Coord::in_range)
+            .ok_or(RuntimeErrorKind::HeadOverflow)?;
+        *self.world.head_mut() = head;
+        Ok(())
+    }
+
+    /// Writes the world to the dump stream, if enabled.
+    fn run_dump(&mut self) -> io::Result<()> {
+        if let Some(dump) = &mut self.dump {
+            writeln!(dump, "\n{}", self.world)?;
+            dump.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Overwrites a cell in the world, recording the write for a [`Tracer`] and the history.
+    fn write(&mut self, coord : Coord, value : C) -> io::Result<()> {
+        if (self.recording) {
+            self.writes.push(CellWrite { coord, value : value.clone() });
+        }
+        self.history.write(coord, || self.world.peek(coord));
+        if let Some(cycles) = &mut self.cycles { cycles.write(coord, &self.world.peek(coord), &value); }
+        if (self.world.writes_output(coord)) { self.interactions += 1; }
+        self.world.insert(coord, value)
+    }
+
+    /// Records a branch taken, if a [`Tracer`] is set.
+    fn branch(&mut self, branch : Branch) {
+        if (self.recording) {
+            self.branches.push(branch);
+        }
     }
 
 
     /// Runs a single instruction in this [`World`].
-    pub fn run_ins(&mut self, ins : &Ins) -> bool {
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Ok(true)` if the script head should move on to the next instruction.
+    /// - `Ok(false)` if the script head was already moved.
+    /// - `Err(_)` if the instruction failed.
+    pub fn run_ins(&mut self, ins : &Ins) -> Result<bool, RuntimeErrorKind> {
 
         match (ins) {
 
-            Ins::MoveHeadOne { adj, dir } => { *self.world.head_mut() += (*adj, *dir,); },
+            Ins::MoveHeadOne { adj, dir } => { self.move_head(Some(Coord::from((*adj, *dir,))))?; },
 
             Ins::MoveHeadDynamic { adj, dir } => {
-                let cell_val = self.world.get(self.world.head()).get_usize_val() as isize;
-                *self.world.head_mut() += Coord::from((*adj, *dir)) * cell_val
+                let cell_val = self.read(self.world.head())?.get_isize_val();
+                self.move_head(Coord::from((*adj, *dir)).checked_mul(cell_val))?;
             },
 
-            Ins::Add { adj } => { self.run_binop(*adj, |a, b| a + b); },
+            Ins::Add { adj } => { self.run_binop(*adj, ArithOp::Add)?; },
 
-            Ins::Sub { adj } => { self.run_binop(*adj, |a, b| a - b); },
+            Ins::Sub { adj } => { self.run_binop(*adj, ArithOp::Sub)?; },
 
-            Ins::Mul { adj } => { self.run_binop(*adj, |a, b| a * b); },
+            Ins::Mul { adj } => { self.run_binop(*adj, ArithOp::Mul)?; },
 
-            Ins::SDiv { adj } => { self.run_binop(*adj, |a, b| a / b); },
+            Ins::SDiv { adj } => { self.run_binop(*adj, ArithOp::Div)?; },
 
             Ins::Swap { adj } => {
-                let head = self.world.head();
-                let (l, r,) = head + *adj;
-                let lv = self.world.get(l);
-                let rv = self.world.get(r);
-                self.world.insert(l, rv);
-                self.world.insert(r, lv);
+                let (l, r,) = self.world.head() + *adj;
+                self.run_swap(l, r)?;
             },
 
             Ins::Noop => { },
 
             Ins::IfNotZeroCond { ins } => {
-                if (self.world.get(self.world.head()) != C::ZERO) {
-                    self.run_ins(ins);
-                }
+                let taken = self.read(self.world.head())? != C::ZERO;
+                self.branch(Branch::Cond { taken });
+                if (taken) { self.run_ins(ins)?; }
             },
 
             Ins::IfZeroCond { ins } => {
-                if (self.world.get(self.world.head()) == C::ZERO) {
-                    self.run_ins(ins);
-                }
+                let taken = self.read(self.world.head())? == C::ZERO;
+                self.branch(Branch::Cond { taken });
+                if (taken) { self.run_ins(ins)?; }
             },
 
             Ins::RandomlyChoose { options } => {
-                if (random::<bool>()) { self.run_ins(&options.0); }
-                else                  { self.run_ins(&options.1); }
+                let first = self.choose();
+                self.branch(Branch::Random { first });
+                if (first) { self.run_ins(&options.0)?; }
+                else       { self.run_ins(&options.1)?; }
             }
 
             Ins::JumpThruCode { dir } => {
-                let cell_val = self.world.get(self.world.head()).get_usize_val();
-                match (dir) {
-                    Dir::L => { self.script_head = self.script_head.saturating_sub(cell_val); },
-                    Dir::R => {
-                        self.script_head = self.script_head.saturating_add(cell_val);
-                    },
-                }
-                return false;
+                self.run_jump(*dir)?;
+                return Ok(false);
             },
 
-            Ins::DumpWorld => {
-                println!("\n{}", self.world);
-            }
+            Ins::DumpWorld => { self.run_dump()?; }
 
         }
-        true
+        Ok(true)
+    }
+
+    /// Runs the bytecode of a single instruction, stopping at the end of the instruction.
+    ///
+    /// ### Returns
+    /// Returns the same as [`Self::run_ins`].
+    fn run_ops(&mut self, ops : &[Op]) -> Result<bool, RuntimeErrorKind> {
+        let mut pc = 0;
+        loop {
+            match (ops[pc]) {
+
+                Op::Move(offset) => { self.move_head(Some(offset))?; },
+
+                Op::MoveDynamic(offset) => {
+                    let cell_val = self.read(self.world.head())?.get_isize_val();
+                    self.move_head(offset.checked_mul(cell_val))?;
+                },
+
+                Op::Arith { op, l, r } => {
+                    let head = self.world.head();
+                    self.run_arith(op, head + l, head + r)?;
+                },
+
+                Op::Swap { l, r } => {
+                    let head = self.world.head();
+                    self.run_swap(head + l, head + r)?;
+                },
+
+                Op::IfNotZero { skip } => {
+                    let taken = self.read(self.world.head())? != C::ZERO;
+                    self.branch(Branch::Cond { taken });
+                    if (! taken) { pc += skip; }
+                },
+
+                Op::IfZero { skip } => {
+                    let taken = self.read(self.world.head())? == C::ZERO;
+                    self.branch(Branch::Cond { taken });
+                    if (! taken) { pc += skip; }
+                },
+
+                Op::IfOutOfRange { low, high, skip } => {
+                    let head = self.world.head();
+                    let out  = [low, high].into_iter().any(|offset| ! head.checked_add(offset).is_some_and(|head| head.in_range()));
+                    if (! out) { pc += skip; }
+                },
+
+                                Op::Choose { skip } => {
+                    let first = self.choose();
+                    self.branch(Branch::Random { first });
+                    if (! first) { pc += skip; }
+                },
+
+                Op::Skip { skip } => { pc += skip; },
+
+                Op::Jump(dir) => { self.run_jump(dir)?; },
+
+                Op::Dump => { self.run_dump()?; },
+
+                Op::Next => { return Ok(true); },
+
+                Op::Jumped => { return Ok(false); },
+
+                Op::Continue(next) => {
+                    self.script_head = next;
+                    return Ok(false);
+                }
+
+            }
+            pc += 1;
+        }
     }
 }
 
-// TODO tests
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ScriptParser;
+    use crate::world::cell::{ U8Cell, U32Cell, I32Cell, I64Cell, BigCell };
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Parses a script from a string.
+    fn parse(src : &[u8]) -> Vec<Ins> {
+        ScriptParser::parse(src.iter().copied().map(Ok)).unwrap()
+    }
+
+    /// Runs a script to completion in memory.
+    fn run<C : Cell>(src : &[u8], input : &str) -> Result<String, RuntimeError> {
+        let (mut runner, output,) = ScriptRunner::<C>::in_memory(parse(src), input);
+        runner.run()?;
+        Ok(String::from_utf8(output.take()).unwrap())
+    }
+
+    #[test]
+    fn hello_world() {
+        let src = include_bytes!("../../samples/simple_hello_world.isolang");
+        assert_eq!(run::<U32Cell>(src, "").unwrap(), "Hello World!");
+        assert_eq!(run::<U8Cell>(src, "").unwrap(), "Hello World!");
+    }
+
+    #[test]
+    fn echo_one() {
+        let src = include_bytes!("../../samples/echo_one.isolang");
+        assert_eq!(run::<U32Cell>(src, "é").unwrap(), "é");
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let err = run::<U32Cell>(b">- >- +-! >- +-!", "").unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::Overflow));
+        assert_eq!(err.script_head, 4);
+        assert_eq!(err.world_head, Coord::from_absolute(6, 0));
+        assert!(run::<I32Cell>(b">- >- +-! >- +-!", "").is_ok());
+        let err = run::<I32Cell>(b">- >- >- +-! >-! *-!", "").unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::DivisionByZero));
+    }
+
+    #[test]
+    fn eof_policies() {
+        // Each swap reads the next input cell into the cell right of the head.
+        let script  = parse(b">- ~- ~- ~-");
+        let read_to = Coord::from_absolute(4, 0);
+        let (mut runner, _,) = ScriptRunner::<I32Cell>::in_memory(script.clone(), "ab");
+        assert!(runner.run_next().unwrap());
+        assert!(runner.run_next().unwrap());
+        assert!(runner.run_next().unwrap());
+        assert!(! runner.run_next().unwrap());
+        assert!(! runner.run_next().unwrap());
+        assert_eq!(runner.world_mut().get(read_to).unwrap(), I32Cell::from('b'));
+        for (eof_policy, expected,) in [(EofPolicy::Zero, I32Cell::ZERO,), (EofPolicy::Sentinel, I32Cell::EOF,)] {
+            let (mut runner, _,) = ScriptRunner::<I32Cell>::in_memory(script.clone(), "ab");
+            runner.world_mut().set_eof_policy(eof_policy);
+            while (runner.run_next().unwrap()) { }
+            assert_eq!(runner.world_mut().get(read_to).unwrap(), expected);
+        }
+        let (mut runner, _,) = ScriptRunner::<I32Cell>::in_memory(script, "ab");
+        runner.world_mut().set_eof_policy(EofPolicy::Error);
+        let err = loop { if let Err(err) = runner.run_next() { break err; } };
+        assert!(matches!(err.kind, RuntimeErrorKind::UnexpectedEof));
+        assert_eq!(err.script_head, 3);
+    }
+
+    #[test]
+    fn head_overflow() {
+        // Multiplies input into a distance too far to move by.
+        let src   = b">- ~- *- >- ~- >-! *- >- *- ;-";
+        let input = "\u{10FFFF}".repeat(3);
+        let (mut runner, _,) = ScriptRunner::<I64Cell>::in_memory(parse(src), input.as_str());
+        runner.set_arith_mode(ArithMode::Saturating);
+        let err = runner.run().unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::HeadOverflow));
+        assert_eq!(err.script_head, 9);
+        let (mut runner, _,) = ScriptRunner::<I64Cell>::in_memory(parse(src), input.as_str());
+        runner.set_arith_mode(ArithMode::Saturating);
+        runner.set_engine(Engine::Bytecode);
+        assert!(matches!(runner.run().unwrap_err().kind, RuntimeErrorKind::HeadOverflow));
+        let (mut runner, _,) = ScriptRunner::<BigCell>::in_memory(parse(src), input.as_str());
+        assert!(matches!(runner.run().unwrap_err().kind, RuntimeErrorKind::HeadOverflow));
+    }
+
+    #[test]
+    fn step_limit() {
+        let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(b">- >- >-"), "");
+        runner.set_step_limit(Some(2));
+        assert!(runner.run_next().unwrap());
+        assert!(runner.run_next().unwrap());
+        assert!(matches!(runner.run_next().unwrap_err().kind, RuntimeErrorKind::StepLimit { steps : 2 }));
+    }
+
+    #[test]
+    fn choices() {
+        let src = b">-# >-# >-# >-#";
+        let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(src), "");
+        runner.set_choices(ScriptedChoices::new([true, false, true]));
+        runner.run().unwrap();
+        let (mut expected, _,) = ScriptRunner::<U32Cell>::in_memory(parse(b">- >- >-"), "");
+        expected.run().unwrap();
+        assert_eq!(runner.world().head(), expected.world().head());
+        let head = |seed| {
+            let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(&src.repeat(16)), "");
+            runner.set_seed(seed);
+            runner.run().unwrap();
+            runner.world().head()
+        };
+        assert_eq!(head(0x5EED), head(0x5EED));
+    }
+
+    #[test]
+    fn tracer() {
+        let steps = Rc::new(RefCell::new(Vec::new()));
+        let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(b">- >- +-? ~/#"), "");
+        runner.set_choices(ScriptedChoices::new([false]));
+        runner.set_tracer(Some(Box::new({
+            let steps = Rc::clone(&steps);
+            move |step : &StepTrace<'_, U32Cell>| steps.borrow_mut().push((
+                step.script_head, step.branches.to_vec(), step.head_before, step.head_after, step.writes.to_vec(),
+            ))
+        })));
+        runner.run().unwrap();
+        let head = Coord::R + Coord::R;
+        assert_eq!(*steps.borrow(), [
+            (0, vec![], Coord::ZERO, Coord::R, vec![],),
+            (1, vec![], Coord::R, head, vec![],),
+            (2, vec![Branch::Cond { taken : true }], head, head, vec![CellWrite { coord : head, value : U32Cell::from('\u{2}') }],),
+            (3, vec![Branch::Random { first : false }], head, head, vec![],)
+        ]);
+    }
+
+    #[test]
+    fn step_back() {
+        let src = include_bytes!("../../samples/echo_one.isolang");
+        let (mut runner, output,) = ScriptRunner::<U32Cell>::in_memory(parse(src), "a");
+        runner.set_history_limit(100);
+        runner.run().unwrap();
+        assert_eq!(runner.history_len(), 7);
+        while (runner.step_back()) { }
+        assert_eq!((runner.steps(), runner.script_head(), runner.world().head(),), (0, 0, Coord::ZERO,));
+        assert_eq!(runner.world().peek(Coord::new(0, -2)), U32Cell::ONE);
+        // The input read is given back, but the output stays written.
+        runner.run().unwrap();
+        assert_eq!(output.take(), b"aa");
+        // Both the `~-` at 3 and the `~/` at 5 write this cell.
+        assert!(runner.step_back_to_write(Coord::new(-2, -2)));
+        assert_eq!(runner.script_head(), 5);
+        assert_eq!(runner.world().peek(Coord::new(-2, -2)), U32Cell::from('a'));
+        assert!(runner.step_back_to_write(Coord::new(-2, -2)));
+        assert_eq!(runner.script_head(), 3);
+        assert_eq!(runner.world().peek(Coord::new(-2, -2)), U32Cell::ONE);
+        assert!(! runner.step_back_to_write(Coord::new(5, 5)));
+        assert_eq!(runner.history_len(), 0);
+    }
+
+    #[test]
+    fn step_back_choices() {
+        let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(&b">-# ".repeat(8)), "");
+        runner.set_history_limit(3);
+        runner.run().unwrap();
+        let head = runner.world().head();
+        assert!(runner.step_back() && runner.step_back() && runner.step_back());
+        assert!(! runner.step_back());
+        runner.set_choices(ScriptedChoices::new([]));
+        runner.run().unwrap();
+        assert_eq!(runner.world().head(), head);
+    }
+
+    #[test]
+    fn dump_world() {
+        let (mut runner, output,) = ScriptRunner::<U32Cell>::in_memory(parse(b">- @ >- @"), "");
+        let dump = MemoryOutput::default();
+        runner.set_dump_output(Some(Box::new(dump.clone())));
+        runner.world_mut().set_display_margin(0);
+        runner.run().unwrap();
+        let dump = String::from_utf8(dump.take()).unwrap();
+        assert_eq!(dump.matches("WORLD").count(), 2);
+        assert!(dump.contains("HEAD(r:2,ul:0)"));
+        assert!(output.take().is_empty());
+        let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(b"@"), "");
+        runner.set_dump_output(None);
+        runner.run().unwrap();
+    }
+
+    #[test]
+    fn run_for() {
+        let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(b">- >- >-"), "");
+        assert!(! runner.run_for(2).unwrap());
+        assert_eq!(runner.steps(), 2);
+        assert!(runner.run_for(1).unwrap());
+        assert!(runner.run_for(5).unwrap());
+        assert_eq!(runner.steps(), 3);
+        // Jumps back onto itself forever.
+        let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(b":!"), "");
+        assert!(! runner.run_for(100).unwrap());
+    }
+
+    #[test]
+    fn timeout() {
+        let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(b":!"), "");
+        runner.set_timeout(Some(Duration::from_millis(10)));
+        let err = runner.run().unwrap_err();
+        assert!(matches!(err.kind, RuntimeErrorKind::Timeout { .. }));
+        assert_eq!(err.script_head, 0);
+    }
+
+    #[test]
+    fn cycle_detection() {
+        let cycle = |src : &[u8], input : &str, jump_by : char| {
+            let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(src), input);
+            runner.set_cycle_detection(true);
+            runner.set_step_limit(Some(10_000));
+            runner.world_mut().insert(Coord::from_absolute(2, 0), U32Cell::from(jump_by)).unwrap();
+            match (runner.run()) {
+                Ok(())   => None,
+                Err(err) => match (err.kind) {
+                    RuntimeErrorKind::Cycle(cycle)       => Some(cycle),
+                    RuntimeErrorKind::StepLimit { .. } => None,
+                    kind                               => panic!("unexpected error: {kind}")
+                }
+            }
+        };
+        assert_eq!(cycle(b":!", "", '\u{1}'), Some(Cycle { length : 1, instructions : vec![0] }));
+        // Moves right and back left, then jumps back by 2 from the cell right of the origin.
+        let found = cycle(b">- >- >-! :!", "", '\u{2}').unwrap();
+        assert_eq!(found, Cycle { length : 3, instructions : vec![1, 2, 3] });
+        assert_eq!(found.to_string(), "the same state repeats every 3 step(s) without any input or output, running instruction(s) 1-3");
+        // Writes output on every round trip, so runs until it is stopped.
+        assert_eq!(cycle(include_bytes!("../../samples/echo_forever.isolang"), "abc", '\u{1}'), None);
+        assert_eq!(cycle(b">- >- >-", "", '\u{1}'), None);
+    }
+
+}
@@ -0,0 +1,132 @@
+//! Peephole optimizer over instruction sequences.
+
+
+use crate::runner::ins::Ins;
+use crate::world::Coord;
+
+
+/// How a single instruction runs once optimized.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(super) enum Peephole {
+
+    /// The instruction runs as it is.
+    Keep,
+
+    /// The instruction starts a run of [`Ins::MoveHeadOne`]s,
+    /// which is merged into a single displacement of the head.
+    /// Adjacent inverse moves cancel out, and a run which cancels out entirely only moves the script head.
+    Move {
+        /// The total displacement of the head, from this instruction to the end of the run.
+        offset : Coord,
+        /// The smallest of each component of the displacement, at any point in the run.
+        low    : Coord,
+        /// The largest of each component of the displacement, at any point in the run.
+        high   : Coord,
+        /// The index of the instruction after the run.
+        next   : usize
+    }
+
+}
+
+
+/// Optimizes a script, keeping one [`Peephole`] for each of its instructions.
+///
+/// Every instruction in a merged run is merged with the rest of the run after it,
+/// so that [`Ins::JumpThruCode`], which counts original instructions, can still land in the middle of a run.
+/// The index of each instruction in the script maps to the optimized instruction which replaces it.
+/// A run which could take the head out of range, part way through, moves one instruction at a time instead,
+/// so that it fails at the same instruction as it would unoptimized.
+///
+/// *Note: No instructions are removed. The [`Ins::Noop`] produced by `#` is always one option of an [`Ins::RandomlyChoose`],
+/// and making the choice is observable through the seed, so it is never safe to remove.*
+pub(super) fn optimize(script : &[Ins]) -> Vec<Peephole> {
+    let mut optimized = vec![Peephole::Keep; script.len()];
+    let mut next      = script.len();
+    let mut offset    = Coord::ZERO;
+    let mut low       = Coord::ZERO;
+    let mut high      = Coord::ZERO;
+    for (index, ins,) in script.iter().enumerate().rev() {
+        match (ins) {
+            Ins::MoveHeadOne { adj, dir } => {
+                let step = Coord::from((*adj, *dir,));
+                offset += step;
+                low  = Coord::new((low.r()  + step.r()).min(0), (low.ul()  + step.ul()).min(0));
+                high = Coord::new((high.r() + step.r()).max(0), (high.ul() + step.ul()).max(0));
+            },
+            _ => {
+                next   = index;
+                offset = Coord::ZERO;
+                low    = Coord::ZERO;
+                high   = Coord::ZERO;
+                continue;
+            }
+        }
+        if (next - index >= 2) {
+            optimized[index] = Peephole::Move { offset, low, high, next };
+        }
+    }
+    optimized
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ScriptParser;
+    use crate::runner::{ ScriptRunner, Engine };
+    use crate::world::cell::U32Cell;
+
+    /// Parses a script from a string.
+    fn parse(src : &[u8]) -> Vec<Ins> {
+        ScriptParser::parse(src.iter().copied().map(Ok)).unwrap()
+    }
+
+    #[test]
+    fn merge_moves() {
+        let script = parse(b">- >-! >/ +- >/ >-#");
+        let up     = Coord::from_absolute(1, 1);
+        assert_eq!(optimize(&script), [
+            Peephole::Move { offset : up, low : Coord::ZERO, high : Coord::new(1, 1), next : 3 },
+            Peephole::Move { offset : up - Coord::R, low : Coord::new(-1, 0), high : Coord::new(0, 1), next : 3 },
+            Peephole::Keep, Peephole::Keep, Peephole::Keep, Peephole::Keep
+        ]);
+        // The `#` makes a random choice, so the moves around it are not merged across it.
+        assert_eq!(optimize(&parse(b">- >-# >-")), [Peephole::Keep; 3]);
+    }
+
+    #[test]
+    fn run_optimized() {
+        let run = |src : &[u8], engine| {
+            let (mut runner, output,) = ScriptRunner::<U32Cell>::in_memory(parse(src), "");
+            runner.set_engine(engine);
+            runner.run().unwrap();
+            (output.take(), runner.world().head(), runner.steps(),)
+        };
+        let src = include_bytes!("../../samples/simple_hello_world.isolang");
+        let (output, head, steps,) = run(src, Engine::Tree);
+        let (opt_output, opt_head, opt_steps,) = run(src, Engine::Optimized);
+        assert_eq!((output, head,), (opt_output, opt_head,));
+        assert!(opt_steps < steps);
+        // Jumps forward 2, into the middle of the run of moves.
+        let src = b">- >- +- : >- >- >/ >/";
+        assert_eq!(run(src, Engine::Tree).1, run(src, Engine::Optimized).1);
+        assert_eq!(run(src, Engine::Optimized).2, 4);
+    }
+
+    #[test]
+    fn run_out_of_range() {
+        let run = |distance, engine| {
+            let (mut runner, _,) = ScriptRunner::<U32Cell>::in_memory(parse(b">- >-! >-!"), "");
+            runner.set_engine(engine);
+            *runner.world_mut().head_mut() = Coord::new(distance, 0);
+            let result = runner.run().map_err(|err| (err.script_head, err.world_head,));
+            (result, runner.world().head(),)
+        };
+        // The run ends in range, but leaves it part way through.
+        let (result, head,) = run(Coord::MAX_DISTANCE, Engine::Optimized);
+        assert_eq!(result, Err((0, Coord::new(Coord::MAX_DISTANCE, 0),)));
+        assert_eq!((result, head,), run(Coord::MAX_DISTANCE, Engine::Tree));
+        assert_eq!(run(Coord::MAX_DISTANCE - 1, Engine::Optimized), run(Coord::MAX_DISTANCE - 1, Engine::Tree));
+    }
+
+}
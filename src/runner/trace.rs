@@ -0,0 +1,196 @@
+//! Tracing of the steps run by a [`ScriptRunner`](crate::runner::ScriptRunner).
+
+
+use crate::world::{ Cell, Coord };
+use crate::world::cell::CellValue;
+use crate::runner::ins::Ins;
+use crate::printer::ScriptPrinter;
+use crate::json::{ JsonStr, JsonCoord };
+use std::io::{ self, Write };
+
+
+/// Everything that happened while running a single step of a script.
+#[derive(Debug)]
+pub struct StepTrace<'l, C : Cell> {
+
+    /// The number of this step, starting at 1.
+    pub step        : u64,
+
+    /// The index of the instruction which was run.
+    pub script_head : usize,
+
+    /// The instruction which was run.
+    pub ins         : &'l Ins,
+
+    /// Which branches were taken by conditional and random instructions, in order.
+    pub branches    : &'l [Branch],
+
+    /// The world head before the instruction was run.
+    pub head_before : Coord,
+
+    /// The world head after the instruction was run.
+    pub head_after  : Coord,
+
+    /// The cells written by the instruction, in order.
+    pub writes      : &'l [CellWrite<C>]
+
+}
+
+/// A branch taken by a conditional or random instruction.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Branch {
+
+    /// An [`Ins::IfNotZeroCond`] or [`Ins::IfZeroCond`] was reached.
+    Cond {
+        /// Whether the contained instruction was run.
+        taken : bool
+    },
+
+    /// An [`Ins::RandomlyChoose`] was reached.
+    Random {
+        /// Whether the first option was chosen, rather than the second.
+        first : bool
+    }
+
+}
+
+/// A cell written by an instruction.
+#[derive(PartialEq, Clone, Debug)]
+pub struct CellWrite<C : Cell> {
+
+    /// The position of the cell.
+    pub coord : Coord,
+
+    /// The value written.
+    pub value : C
+
+}
+
+
+/// Receives a [`StepTrace`] after each step run by a [`ScriptRunner`](crate::runner::ScriptRunner).
+///
+/// This is implemented for closures taking a [`StepTrace`].
+pub trait Tracer<C : Cell> {
+
+    /// Handles a step which was run.
+    fn trace(&mut self, step : &StepTrace<'_, C>);
+
+}
+
+impl<C : Cell, F : FnMut(&StepTrace<'_, C>)> Tracer<C> for F {
+    fn trace(&mut self, step : &StepTrace<'_, C>) { self(step) }
+}
+
+
+/// How a [`TraceWriter`] formats each step.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum TraceFormat {
+    /// One human readable line per step.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    JsonLines
+}
+
+/// A [`Tracer`] which writes each step to an output stream.
+///
+/// *Note: Errors writing the trace are ignored.*
+pub struct TraceWriter<W : Write> {
+
+    /// The stream to write to.
+    output : W,
+
+    /// How each step is formatted.
+    format : TraceFormat
+
+}
+
+impl<W : Write> TraceWriter<W> {
+
+    /// Creates a tracer which writes each step to `output`.
+    pub fn new(output : W, format : TraceFormat) -> Self {
+        Self { output, format }
+    }
+
+    /// Writes a step as a single human readable line.
+    fn write_text<C : Cell>(&mut self, step : &StepTrace<'_, C>) -> io::Result<()> {
+        write!(self.output, "{:>6} [{}] {}", step.step, step.script_head, ScriptPrinter::describe_ins(step.ins))?;
+        for branch in step.branches { match (branch) {
+            Branch::Cond   { taken } => write!(self.output, " {}", if (*taken) { "taken" } else { "not-taken" })?,
+            Branch::Random { first } => write!(self.output, " {}", if (*first) { "first" } else { "second" })?
+        } }
+        write!(self.output, " head {}", step.head_before)?;
+        if (step.head_after != step.head_before) {
+            write!(self.output, " -> {}", step.head_after)?;
+        }
+        for write in step.writes {
+            write!(self.output, " {}={}", write.coord, CellValue(&write.value))?;
+        }
+        writeln!(self.output)
+    }
+
+    /// Writes a step as a single line JSON object.
+    fn write_json<C : Cell>(&mut self, step : &StepTrace<'_, C>) -> io::Result<()> {
+        write!(self.output, "{{\"step\":{},\"script_head\":{},\"ins\":{},\"branches\":[",
+            step.step, step.script_head, JsonStr(&ScriptPrinter::describe_ins(step.ins))
+        )?;
+        for (i, branch,) in step.branches.iter().enumerate() {
+            if (i > 0) { write!(self.output, ",")?; }
+            match (branch) {
+                Branch::Cond   { taken } => write!(self.output, "{{\"cond\":{taken}}}")?,
+                Branch::Random { first } => write!(self.output, "{{\"random_first\":{first}}}")?
+            }
+        }
+        write!(self.output, "],\"head_before\":{},\"head_after\":{},\"writes\":[",
+            JsonCoord(step.head_before), JsonCoord(step.head_after)
+        )?;
+        for (i, write,) in step.writes.iter().enumerate() {
+            if (i > 0) { write!(self.output, ",")?; }
+            write!(self.output, "{{\"coord\":{},\"value\":{}}}", JsonCoord(write.coord), CellValue(&write.value))?;
+        }
+        writeln!(self.output, "]}}")
+    }
+
+}
+
+impl<C : Cell, W : Write> Tracer<C> for TraceWriter<W> {
+    fn trace(&mut self, step : &StepTrace<'_, C>) {
+        let _ = match (self.format) {
+            TraceFormat::Text      => self.write_text(step),
+            TraceFormat::JsonLines => self.write_json(step)
+        };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::cell::U32Cell;
+
+    #[test]
+    fn write_formats() {
+        let ins    = Ins::IfNotZeroCond { ins : Box::new(Ins::Add { adj : crate::world::Adj::ULDR }) };
+        let writes = [CellWrite { coord : Coord::ZERO + Coord::R, value : U32Cell::from('H') }];
+        let step   = StepTrace {
+            step        : 3,
+            script_head : 2,
+            ins         : &ins,
+            branches    : &[Branch::Cond { taken : true }],
+            head_before : Coord::R,
+            head_after  : Coord::R,
+            writes      : &writes
+        };
+        let mut text = TraceWriter::new(Vec::new(), TraceFormat::Text);
+        text.trace(&step);
+        assert_eq!(String::from_utf8(text.output).unwrap(), "     3 [2] +\\? taken head (r:1,ul:0) (r:1,ul:0)=72\n");
+        let mut json = TraceWriter::new(Vec::new(), TraceFormat::JsonLines);
+        json.trace(&step);
+        assert_eq!(String::from_utf8(json.output).unwrap(), concat!(
+            "{\"step\":3,\"script_head\":2,\"ins\":\"+\\\\?\",\"branches\":[{\"cond\":true}],",
+            "\"head_before\":{\"r\":1,\"ul\":0},\"head_after\":{\"r\":1,\"ul\":0},",
+            "\"writes\":[{\"coord\":{\"r\":1,\"ul\":0},\"value\":72}]}\n"
+        ));
+    }
+
+}
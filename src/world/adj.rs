@@ -6,7 +6,7 @@
 pub enum Adj {
 
     /// Left/right
-    /// ```
+    /// ```text
     ///  . .
     /// + @ +
     ///  . .
@@ -14,7 +14,7 @@ pub enum Adj {
     LR,
 
     /// Up-left/down-right
-    /// ```
+    /// ```text
     ///  + .
     /// . @ .
     ///  . +
@@ -22,7 +22,7 @@ pub enum Adj {
     ULDR,
 
     /// Down-left/up-right
-    /// ```
+    /// ```text
     ///  . +
     /// . @ .
     ///  + .
@@ -30,7 +30,7 @@ pub enum Adj {
     DLUR,
 
     /// Up-left/up-right
-    /// ```
+    /// ```text
     ///  + +
     /// . @ .
     ///  . .
@@ -38,7 +38,7 @@ pub enum Adj {
     U2,
 
     /// Down-left/down-right
-    /// ```
+    /// ```text
     ///  . .
     /// . @ .
     ///  + +
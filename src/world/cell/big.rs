@@ -0,0 +1,168 @@
+//! A cell containing an arbitrary-precision integer.
+//!
+//! Each UTF8 character read from input is one cell, containing its Unicode scalar value.
+//! Each cell written to output is one UTF8 character, or nothing if it is not a Unicode
+//! scalar value. Negative cells are never Unicode scalar values.
+
+
+use crate::world::cell::{ Cell, ArithOp, ArithMode, ArithError, CellReadError };
+use crate::world::cell::read::read_char;
+use core::fmt;
+use std::io::Read;
+use num_bigint::BigInt;
+
+
+/// A cell containing an arbitrary-precision integer.
+///
+/// Arithmetic never overflows, so the [`ArithMode`] is ignored.
+#[derive(PartialEq, Eq, Clone)]
+pub struct BigCell(Repr);
+
+/// How the value of a [`BigCell`] is stored.
+///
+/// *Note: Values which fit in an [`i64`] are always [`Repr::Small`], so that each value has one representation.*
+#[derive(PartialEq, Eq, Clone)]
+enum Repr {
+
+    /// A value which fits in an [`i64`].
+    Small(i64),
+
+    /// A value which does not fit in an [`i64`].
+    Big(BigInt)
+
+}
+
+impl BigCell {
+
+    /// Converts this cell to a [`BigInt`].
+    pub fn to_big(&self) -> BigInt { match (&self.0) {
+        Repr::Small(value) => BigInt::from(*value),
+        Repr::Big(value)   => value.clone()
+    } }
+
+    /// Converts this cell to an [`i64`], if it fits.
+    pub fn to_small(&self) -> Option<i64> { match (&self.0) {
+        Repr::Small(value) => Some(*value),
+        Repr::Big(_)       => None
+    } }
+
+}
+
+impl From<i64> for BigCell {
+    fn from(value : i64) -> Self { Self(Repr::Small(value)) }
+}
+
+/// Keeps the value small if it fits in an [`i64`].
+impl From<BigInt> for BigCell {
+    fn from(value : BigInt) -> Self {
+        Self(i64::try_from(&value).map_or(Repr::Big(value), Repr::Small))
+    }
+}
+
+impl From<char> for BigCell {
+    fn from(value : char) -> Self { Self::from(value as i64) }
+}
+
+impl Cell for BigCell {
+
+    const ZERO : Self = Self(Repr::Small(0));
+    const ONE  : Self = Self(Repr::Small(1));
+    const EOF  : Self = Self(Repr::Small(-1));
+    const BOUNDS : Option<(i128, i128,)> = None;
+
+    fn get_usize_val(&self) -> usize {
+        match (&self.0) {
+            Repr::Small(value) => usize::try_from((*value).max(0)).unwrap_or(usize::MAX),
+            Repr::Big(value)   => if (*value < BigInt::ZERO) { 0 } else { usize::MAX }
+        }
+    }
+
+    fn get_isize_val(&self) -> isize {
+        match (&self.0) {
+            Repr::Small(value) => isize::try_from(*value).unwrap_or(if (*value < 0) { isize::MIN } else { isize::MAX }),
+            Repr::Big(value)   => if (*value < BigInt::ZERO) { isize::MIN } else { isize::MAX }
+        }
+    }
+
+    fn fmt_value(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.0) {
+            Repr::Small(value) => write!(f, "{}", value),
+            Repr::Big(value)   => write!(f, "{}", value)
+        }
+    }
+
+    fn arith(self, op : ArithOp, rhs : Self, _ : ArithMode) -> Result<Self, ArithError> {
+        if let (Some(lhs), Some(rhs),) = (self.to_small(), rhs.to_small(),) {
+            match (int_arith!(lhs, op, rhs, ArithMode::Checked)) {
+                Ok(value)                 => { return Ok(Self::from(value)); },
+                Err(ArithError::Overflow) => { },
+                Err(err)                  => { return Err(err); }
+            }
+        }
+        let (lhs, rhs,) = (self.to_big(), rhs.to_big(),);
+        Ok(Self::from(match (op) {
+            ArithOp::Add => lhs + rhs,
+            ArithOp::Sub => lhs - rhs,
+            ArithOp::Mul => lhs * rhs,
+            ArithOp::Div => {
+                if (rhs == BigInt::ZERO) { return Err(ArithError::DivisionByZero); }
+                lhs / rhs
+            }
+        }))
+    }
+
+    fn read_from(input : &mut dyn Read) -> Option<Result<Self, CellReadError>> {
+        read_char(input).map(|value| value.map(Self::from))
+    }
+
+}
+
+impl fmt::Display for BigCell {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.to_small().and_then(|value| u32::try_from(value).ok()).and_then(char::from_u32)) {
+            Some(ch) => write!(f, "{}", ch),
+            None     => Ok(())
+        }
+    }
+}
+
+impl fmt::Debug for BigCell {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_and_shrinks() {
+        let max = BigCell::from(i64::MAX);
+        let big = max.clone().arith(ArithOp::Add, BigCell::ONE, ArithMode::Checked).unwrap();
+        assert_eq!(big.to_big(), BigInt::from(i64::MAX) + 1);
+        assert_eq!(big.to_small(), None);
+        assert_eq!(big.get_isize_val(), isize::MAX);
+        assert_eq!(big.arith(ArithOp::Sub, BigCell::ONE, ArithMode::Checked), Ok(max));
+        let min = BigCell::from(i64::MIN);
+        assert_eq!(min.arith(ArithOp::Div, BigCell::from(-1), ArithMode::Checked), Ok(BigCell::from(-BigInt::from(i64::MIN))));
+        assert_eq!(BigCell::ONE.arith(ArithOp::Div, BigCell::ZERO, ArithMode::Wrapping), Err(ArithError::DivisionByZero));
+    }
+
+    #[test]
+    fn factorial() {
+        let mut value = BigCell::ONE;
+        for n in 1..=30 {
+            value = value.arith(ArithOp::Mul, BigCell::from(n), ArithMode::Checked).unwrap();
+        }
+        assert_eq!(value.to_big().to_string(), "265252859812191058636308480000000");
+    }
+
+    #[test]
+    fn normalized() {
+        assert_eq!(BigCell::from(BigInt::from(1)), BigCell::ONE);
+        assert_eq!(BigCell::from(BigInt::from(i64::MIN)).to_small(), Some(i64::MIN));
+    }
+
+}
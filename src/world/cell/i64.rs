@@ -0,0 +1,61 @@
+//! A cell containing an [`i64`].
+//!
+//! Each UTF8 character read from input is one cell, containing its Unicode scalar value.
+//! Each cell written to output is one UTF8 character, or nothing if it is not a Unicode
+//! scalar value. Negative cells are never Unicode scalar values.
+
+
+use crate::world::cell::{ Cell, ArithOp, ArithMode, ArithError, CellReadError };
+use crate::world::cell::read::read_char;
+use core::fmt;
+use std::io::Read;
+
+
+/// A cell containing an [`i64`].
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[repr(transparent)]
+pub struct I64Cell(i64);
+
+impl From<char> for I64Cell {
+    fn from(value : char) -> Self { Self(value as i64) }
+}
+
+impl Cell for I64Cell {
+
+    const ZERO : Self = Self(0);
+    const ONE  : Self = Self(1);
+    const EOF  : Self = Self(-1);
+    const BOUNDS : Option<(i128, i128,)> = Some((i64::MIN as i128, i64::MAX as i128,));
+
+    fn get_usize_val(&self) -> usize {
+        usize::try_from(self.0.max(0)).unwrap_or(usize::MAX)
+    }
+
+    fn get_isize_val(&self) -> isize {
+        isize::try_from(self.0).unwrap_or(if (self.0 < 0) { isize::MIN } else { isize::MAX })
+    }
+
+    fn arith(self, op : ArithOp, rhs : Self, mode : ArithMode) -> Result<Self, ArithError> {
+        int_arith!(self.0, op, rhs.0, mode).map(Self)
+    }
+
+    fn read_from(input : &mut dyn Read) -> Option<Result<Self, CellReadError>> {
+        read_char(input).map(|value| value.map(Self::from))
+    }
+
+}
+
+impl fmt::Display for I64Cell {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (u32::try_from(self.0).ok().and_then(char::from_u32)) {
+            Some(ch) => write!(f, "{}", ch),
+            None     => Ok(())
+        }
+    }
+}
+
+impl fmt::Debug for I64Cell {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
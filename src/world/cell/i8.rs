@@ -0,0 +1,76 @@
+//! A cell containing an [`i8`].
+//!
+//! Each byte read from input is one cell, reinterpreted as two's complement.
+//! Each cell written to output is one byte, reinterpreted the same way.
+
+
+use crate::world::cell::{ Cell, ArithOp, ArithMode, ArithError, CellReadError };
+use crate::world::cell::read::read_byte;
+use core::fmt;
+use std::io::Read;
+
+
+/// A cell containing an [`i8`].
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[repr(transparent)]
+pub struct I8Cell(i8);
+
+impl From<u8> for I8Cell {
+    fn from(value : u8) -> Self { Self(value as i8) }
+}
+
+impl Cell for I8Cell {
+
+    const ZERO : Self = Self(0);
+    const ONE  : Self = Self(1);
+    const EOF  : Self = Self(-1);
+    const BOUNDS : Option<(i128, i128,)> = Some((i8::MIN as i128, i8::MAX as i128,));
+
+    fn get_usize_val(&self) -> usize {
+        self.0.max(0) as usize
+    }
+
+    fn get_isize_val(&self) -> isize {
+        self.0 as isize
+    }
+
+    fn arith(self, op : ArithOp, rhs : Self, mode : ArithMode) -> Result<Self, ArithError> {
+        int_arith!(self.0, op, rhs.0, mode).map(Self)
+    }
+
+    fn read_from(input : &mut dyn Read) -> Option<Result<Self, CellReadError>> {
+        read_byte(input).map(|value| value.map(Self::from))
+    }
+
+}
+
+impl fmt::Display for I8Cell {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0 as u8 as char)
+    }
+}
+
+impl fmt::Debug for I8Cell {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_arith() {
+        let (a, b,) = (I8Cell(-7), I8Cell(2),);
+        assert_eq!(a.arith(ArithOp::Div, b, ArithMode::Checked), Ok(I8Cell(-3)));
+        assert_eq!(b.arith(ArithOp::Sub, I8Cell(3), ArithMode::Checked), Ok(I8Cell(-1)));
+        assert_eq!(I8Cell(i8::MIN).arith(ArithOp::Div, I8Cell(-1), ArithMode::Checked), Err(ArithError::Overflow));
+        assert_eq!(I8Cell(i8::MIN).arith(ArithOp::Div, I8Cell(-1), ArithMode::Saturating), Ok(I8Cell(i8::MAX)));
+        assert_eq!(a.get_usize_val(), 0);
+        assert_eq!(a.get_isize_val(), -7);
+        assert_eq!(I8Cell::from(0xFF), I8Cell(-1));
+    }
+
+}
@@ -2,66 +2,209 @@
 
 
 use core::fmt;
-use core::ops::{ Add, Sub, Mul, Div };
-use std::io;
+use std::error::Error;
+use std::io::{ self, Read };
 use std::str::Utf8Error;
 
 
+/// Applies an [`ArithOp`] to two primitive integers, following an [`ArithMode`].
+///
+/// Evaluates to a `Result<_, ArithError>` of the primitive integer type.
+macro_rules! int_arith { ($lhs:expr, $op:expr, $rhs:expr, $mode:expr) => { {
+    let (lhs, rhs,) = ($lhs, $rhs,);
+    if ($op == ArithOp::Div && rhs == 0) {
+        Err(ArithError::DivisionByZero)
+    } else { match ($mode) {
+        ArithMode::Wrapping => Ok(match ($op) {
+            ArithOp::Add => lhs.wrapping_add(rhs),
+            ArithOp::Sub => lhs.wrapping_sub(rhs),
+            ArithOp::Mul => lhs.wrapping_mul(rhs),
+            ArithOp::Div => lhs.wrapping_div(rhs)
+        }),
+        ArithMode::Saturating => Ok(match ($op) {
+            ArithOp::Add => lhs.saturating_add(rhs),
+            ArithOp::Sub => lhs.saturating_sub(rhs),
+            ArithOp::Mul => lhs.saturating_mul(rhs),
+            ArithOp::Div => lhs.saturating_div(rhs)
+        }),
+        ArithMode::Checked => match ($op) {
+            ArithOp::Add => lhs.checked_add(rhs),
+            ArithOp::Sub => lhs.checked_sub(rhs),
+            ArithOp::Mul => lhs.checked_mul(rhs),
+            ArithOp::Div => lhs.checked_div(rhs)
+        }.ok_or(ArithError::Overflow)
+    } }
+} } }
+
+
+pub mod read;
+
 mod u8;
 pub use u8::U8Cell;
 
 mod u32;
 pub use u32::U32Cell;
 
+mod i8;
+pub use i8::I8Cell;
+
+mod i32;
+pub use i32::I32Cell;
+
+mod i64;
+pub use i64::I64Cell;
+
+mod big;
+pub use big::BigCell;
+
 
 
 /// A cell that can be in a [`World`].
 pub trait Cell
 where Self
     : PartialEq
-    + Clone + Copy
+    + Clone
     + fmt::Display
     + fmt::Debug
-    + Add<Self, Output = Self>
-    + Sub<Self, Output = Self>
-    + Mul<Self, Output = Self>
-    + Div<Self, Output = Self>
 {
 
-    /// An iterator over values of this cell type read from stdin.
-    type StdinReader : Iterator<Item = Result<Self, CellStdinReadError>>;
-
     /// A cell containing value zero.
     const ZERO : Self;
     /// A cell containing value one.
     const ONE  : Self;
+    /// A cell containing the sentinel value read at the end of input,
+    /// `-1` for signed cells and the maximum value for unsigned cells.
+    const EOF  : Self;
+    /// The smallest and largest values a cell can hold, or `None` if unbounded.
+    const BOUNDS : Option<(i128, i128,)>;
 
     /// Converts value to usize
+    ///
+    /// *Note: Negative values are converted to zero, and values too large are clamped.*
     fn get_usize_val(&self) -> usize;
 
-    /// Creates an iterator over values of this cell type read from stdin.
-    fn create_stdin_reader() -> Self::StdinReader;
+    /// Converts value to isize
+    ///
+    /// *Note: Values out of range are clamped.*
+    fn get_isize_val(&self) -> isize;
+
+    /// Writes the numeric value of this cell.
+    fn fmt_value(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get_isize_val())
+    }
+
+    /// Applies an arithmetic operation to this cell (left) and another (right).
+    ///
+    /// `mode` decides what happens when the result does not fit in a cell.
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Ok(_)` with the resulting cell.
+    /// - `Err(_)` if the result is undefined, or does not fit in a cell under [`ArithMode::Checked`].
+    fn arith(self, op : ArithOp, rhs : Self, mode : ArithMode) -> Result<Self, ArithError>;
+
+    /// Reads a single cell from an input stream.
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Some(Ok(_))` if a cell was read.
+    /// - `Some(Err(_))` if reading failed.
+    /// - `None` if the end of the stream was reached.
+    fn read_from(input : &mut dyn Read) -> Option<Result<Self, CellReadError>>;
+
+}
+
+
+
+/// Displays the numeric value of a cell, using [`Cell::fmt_value`].
+pub struct CellValue<'l, C : Cell>(pub &'l C);
 
+impl<C : Cell> fmt::Display for CellValue<'_, C> {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_value(f)
+    }
+}
+
+
+/// An arithmetic operation on two cells.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ArithOp {
+    /// Addition.
+    Add,
+    /// Subtraction, left minus right.
+    Sub,
+    /// Multiplication.
+    Mul,
+    /// Division, left divided by right.
+    Div
+}
+
+/// What happens when the result of an arithmetic operation does not fit in a cell.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum ArithMode {
+    /// The result wraps around at the bounds of the cell.
+    Wrapping,
+    /// The result is clamped to the bounds of the cell.
+    Saturating,
+    /// An [`ArithError::Overflow`] is raised.
+    #[default]
+    Checked
+}
+
+/// An arithmetic operation on two cells could not produce a cell.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ArithError {
+    /// The right cell of a division was zero.
+    DivisionByZero,
+    /// The result does not fit in a cell.
+    Overflow
+}
+
+impl fmt::Display for ArithError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::DivisionByZero => write!(f, "division by zero"),
+        Self::Overflow       => write!(f, "arithmetic overflow")
+    } }
 }
 
+impl Error for ArithError { }
 
 
-/// An error occured while reading characters from a reader.
-pub enum CellStdinReadError {
+/// An error occured while reading cells from an input stream.
+#[derive(Debug)]
+pub enum CellReadError {
     /// An error was returned by the reader.
     Io(io::Error),
     /// An invalid UTF8 sequence was found.
-    Utf8(Utf8Error)
+    Utf8(Utf8Error),
+    /// The end of the stream was reached.
+    Eof
 }
 
-impl From<io::Error> for CellStdinReadError {
+impl From<io::Error> for CellReadError {
     fn from(value : io::Error) -> Self {
         Self::Io(value)
     }
 }
 
-impl From<Utf8Error> for CellStdinReadError {
+impl From<Utf8Error> for CellReadError {
     fn from(value : Utf8Error) -> Self {
         Self::Utf8(value)
     }
 }
+
+impl fmt::Display for CellReadError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::Io(err)   => write!(f, "failed to read input: {err}"),
+        Self::Utf8(err) => write!(f, "invalid UTF-8 in input: {err}"),
+        Self::Eof       => write!(f, "unexpected end of input")
+    } }
+}
+
+impl Error for CellReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> { match (self) {
+        Self::Io(err)   => Some(err),
+        Self::Utf8(err) => Some(err),
+        Self::Eof       => None
+    } }
+}
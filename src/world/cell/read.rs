@@ -0,0 +1,99 @@
+//! Reading cells from an input stream.
+
+
+use crate::world::cell::CellReadError;
+use std::io::{ Read, ErrorKind };
+
+
+/// Reads a single byte from an input stream, retrying if reading was interrupted.
+///
+/// ### Returns
+/// Returns:
+/// - `Some(Ok(_))` if a byte was read.
+/// - `Some(Err(_))` if reading failed.
+/// - `None` if the end of the stream was reached.
+pub fn read_byte(input : &mut dyn Read) -> Option<Result<u8, CellReadError>> {
+    let mut buf = [0u8];
+    loop { match (input.read(&mut buf)) {
+        Ok(0)    => { return None; },
+        Ok(1)    => { return Some(Ok(buf[0])); },
+        Ok(_)    => unreachable!(),
+        Err(err) if (err.kind() == ErrorKind::Interrupted) => { },
+        Err(err) => { return Some(Err(CellReadError::Io(err))); }
+    } }
+}
+
+/// Reads a single UTF8 character from an input stream.
+///
+/// ### Returns
+/// Returns:
+/// - `Some(Ok(_))` if a character was read.
+/// - `Some(Err(_))` if reading failed, or an invalid UTF8 sequence was found,
+///   including one cut off by the end of the stream.
+/// - `None` if the end of the stream was reached before any byte was read.
+pub fn read_char(input : &mut dyn Read) -> Option<Result<char, CellReadError>> {
+    let mut bytes = [0u8; 4];
+    for n in 0..4 {
+        match (read_byte(input)) {
+            Some(Ok(byte)) => { bytes[n] = byte; },
+            Some(Err(err)) => { return Some(Err(err)); },
+            None if (n == 0) => { return None; },
+            None => { return Some(Err(str::from_utf8(&bytes[..n]).unwrap_err().into())); }
+        }
+        match (str::from_utf8(&bytes[..=n])) {
+            Ok(s) => { return Some(Ok(s.chars().next().unwrap())); },
+            Err(err) if (err.error_len().is_some()) => {
+                return Some(Err(err.into()));
+            },
+            _ => { }
+        }
+    }
+    unreachable!()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_chars() {
+        let mut input : &[u8] = "aé€\u{1F986}".as_bytes();
+        assert_eq!(read_char(&mut input).unwrap().unwrap(), 'a');
+        assert_eq!(read_char(&mut input).unwrap().unwrap(), 'é');
+        assert_eq!(read_char(&mut input).unwrap().unwrap(), '€');
+        assert_eq!(read_char(&mut input).unwrap().unwrap(), '\u{1F986}');
+        assert!(read_char(&mut input).is_none());
+        let mut input : &[u8] = &[0xFF];
+        assert!(matches!(read_char(&mut input), Some(Err(CellReadError::Utf8(_)))));
+        // A sequence cut off by the end of the stream.
+        let mut input : &[u8] = &"€".as_bytes()[..2];
+        assert!(matches!(read_char(&mut input), Some(Err(CellReadError::Utf8(_)))));
+        assert!(read_char(&mut input).is_none());
+    }
+
+    /// A stream which is interrupted before every byte.
+    struct Interrupting<'l> {
+        /// The bytes left to read.
+        bytes       : &'l [u8],
+        /// Whether the next read is interrupted.
+        interrupted : bool
+    }
+
+    impl Read for Interrupting<'_> {
+        fn read(&mut self, buf : &mut [u8]) -> std::io::Result<usize> {
+            self.interrupted = ! self.interrupted;
+            if (self.interrupted) { return Err(ErrorKind::Interrupted.into()); }
+            self.bytes.read(&mut buf[..1])
+        }
+    }
+
+    #[test]
+    fn read_interrupted() {
+        let mut input = Interrupting { bytes : "aé".as_bytes(), interrupted : false };
+        assert_eq!(read_char(&mut input).unwrap().unwrap(), 'a');
+        assert_eq!(read_char(&mut input).unwrap().unwrap(), 'é');
+        assert!(read_char(&mut input).is_none());
+    }
+
+}
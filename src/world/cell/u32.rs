@@ -1,10 +1,13 @@
-//! A cell containing a [`u8`].
+//! A cell containing a [`u32`].
+//!
+//! Each UTF8 character read from input is one cell, containing its Unicode scalar value.
+//! Each cell written to output is one UTF8 character, or nothing if it is not a Unicode scalar value.
 
 
-use crate::world::{ Cell, CellStdinReadError };
-use core::ops::{ Add, Sub, Mul, Div };
+use crate::world::cell::{ Cell, ArithOp, ArithMode, ArithError, CellReadError };
+use crate::world::cell::read::read_char;
 use core::fmt;
-use std::io::{ self, Read, BufReader, Stdin };
+use std::io::Read;
 
 
 /// A cell containing a [`u32`].
@@ -17,47 +20,31 @@ impl Default for U32Cell {
     fn default() -> Self { Self(1) }
 }
 
-impl Add for U32Cell {
-    type Output = Self;
-    fn add(self, rhs : Self) -> Self::Output {
-        Self(self.0 + rhs.0)
-    }
-}
-
-impl Sub for U32Cell {
-    type Output = Self;
-    fn sub(self, rhs : Self) -> Self::Output {
-        Self(self.0 - rhs.0)
-    }
-}
-
-impl Mul for U32Cell {
-    type Output = Self;
-    fn mul(self, rhs : Self) -> Self::Output {
-        Self(self.0 * rhs.0)
-    }
-}
-
-impl Div for U32Cell {
-    type Output = Self;
-    fn div(self, rhs : Self) -> Self::Output {
-        Self(self.0 / rhs.0)
-    }
+impl From<char> for U32Cell {
+    fn from(value : char) -> Self { Self(value as u32) }
 }
 
 impl Cell for U32Cell {
 
-    type StdinReader = StdinCharReader;
-
     const ZERO : Self = Self(0);
     const ONE  : Self = Self(1);
+    const EOF  : Self = Self(u32::MAX);
+    const BOUNDS : Option<(i128, i128,)> = Some((u32::MIN as i128, u32::MAX as i128,));
 
     fn get_usize_val(&self) -> usize {
         self.0 as usize
     }
 
-    fn create_stdin_reader() -> Self::StdinReader {
-        StdinCharReader::default()
+    fn get_isize_val(&self) -> isize {
+        isize::try_from(self.0).unwrap_or(isize::MAX)
+    }
+
+    fn arith(self, op : ArithOp, rhs : Self, mode : ArithMode) -> Result<Self, ArithError> {
+        int_arith!(self.0, op, rhs.0, mode).map(Self)
+    }
+
+    fn read_from(input : &mut dyn Read) -> Option<Result<Self, CellReadError>> {
+        read_char(input).map(|value| value.map(Self::from))
     }
 
 }
@@ -76,40 +63,3 @@ impl fmt::Debug for U32Cell {
         write!(f, "{}", self)
     }
 }
-
-/// An iterator over UTF8 characters read from stdin.
-pub struct StdinCharReader {
-    /// Buffered stdin reader.
-    reader : BufReader<Stdin>
-}
-
-impl Default for StdinCharReader {
-    fn default() -> Self {
-        Self { reader : BufReader::new(io::stdin()) }
-    }
-}
-
-impl Iterator for StdinCharReader {
-    type Item = Result<U32Cell, CellStdinReadError>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut bytes = [0u8; 4];
-        for n in 0..4 {
-            let mut buf = [0u8];
-            match (self.reader.read(&mut buf)) {
-                Ok(0)    => { return None; },
-                Ok(1)    => { bytes[n] = buf[0]; },
-                Ok(_)    => unreachable!(),
-                Err(err) => { return Some(Err(err.into())); }
-            }
-            match (str::from_utf8(&bytes[..=n])) {
-                Ok(s) => { return Some(Ok(U32Cell(s.chars().next().unwrap() as u32))); },
-                Err(err) if (err.error_len().is_some()) => {
-                    return Some(Err(err.into()));
-                },
-                _ => { }
-            }
-        }
-        unreachable!()
-    }
-}
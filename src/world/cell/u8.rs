@@ -1,10 +1,12 @@
 //! A cell containing a [`u8`].
+//!
+//! Each byte read from input is one cell, and each cell written to output is one byte.
 
 
-use crate::world::{ Cell, CellStdinReadError };
-use core::ops::{ Add, Sub, Mul, Div };
+use crate::world::cell::{ Cell, ArithOp, ArithMode, ArithError, CellReadError };
+use crate::world::cell::read::read_byte;
 use core::fmt;
-use std::io::{ self, BufReader, Stdin, Read };
+use std::io::Read;
 
 
 /// A cell containing a [`u8`].
@@ -12,47 +14,31 @@ use std::io::{ self, BufReader, Stdin, Read };
 #[repr(transparent)]
 pub struct U8Cell(u8);
 
-impl Add for U8Cell {
-    type Output = Self;
-    fn add(self, rhs : Self) -> Self::Output {
-        Self(self.0 + rhs.0)
-    }
-}
-
-impl Sub for U8Cell {
-    type Output = Self;
-    fn sub(self, rhs : Self) -> Self::Output {
-        Self(self.0 - rhs.0)
-    }
-}
-
-impl Mul for U8Cell {
-    type Output = Self;
-    fn mul(self, rhs : Self) -> Self::Output {
-        Self(self.0 * rhs.0)
-    }
-}
-
-impl Div for U8Cell {
-    type Output = Self;
-    fn div(self, rhs : Self) -> Self::Output {
-        Self(self.0 / rhs.0)
-    }
+impl From<u8> for U8Cell {
+    fn from(value : u8) -> Self { Self(value) }
 }
 
 impl Cell for U8Cell {
 
-    type StdinReader = StdinU8Reader;
-
     const ZERO : Self = Self(0);
     const ONE  : Self = Self(1);
+    const EOF  : Self = Self(u8::MAX);
+    const BOUNDS : Option<(i128, i128,)> = Some((u8::MIN as i128, u8::MAX as i128,));
 
     fn get_usize_val(&self) -> usize {
         self.0 as usize
     }
 
-    fn create_stdin_reader() -> Self::StdinReader {
-        StdinU8Reader::default()
+    fn get_isize_val(&self) -> isize {
+        self.0 as isize
+    }
+
+    fn arith(self, op : ArithOp, rhs : Self, mode : ArithMode) -> Result<Self, ArithError> {
+        int_arith!(self.0, op, rhs.0, mode).map(Self)
+    }
+
+    fn read_from(input : &mut dyn Read) -> Option<Result<Self, CellReadError>> {
+        read_byte(input).map(|value| value.map(Self::from))
     }
 
 }
@@ -69,28 +55,22 @@ impl fmt::Debug for U8Cell {
     }
 }
 
-/// An iterator over u8s read from stdin.
-pub struct StdinU8Reader {
-    /// Buffered stdin reader.
-    reader : BufReader<Stdin>
-}
-
-impl Default for StdinU8Reader {
-    fn default() -> Self {
-        Self { reader : BufReader::new(io::stdin()) }
-    }
-}
-
-impl Iterator for StdinU8Reader {
-    type Item = Result<U8Cell, CellStdinReadError>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buf = [0u8];
-        match (self.reader.read(&mut buf)) {
-            Ok(0)    => None,
-            Ok(1)    => Some(Ok(U8Cell(buf[0]))),
-            Ok(_)    => unreachable!(),
-            Err(err) => Some(Err(CellStdinReadError::Io(err)))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arith_modes() {
+        let (a, b,) = (U8Cell(200), U8Cell(100),);
+        assert_eq!(a.arith(ArithOp::Add, b, ArithMode::Wrapping), Ok(U8Cell(44)));
+        assert_eq!(a.arith(ArithOp::Add, b, ArithMode::Saturating), Ok(U8Cell(255)));
+        assert_eq!(a.arith(ArithOp::Add, b, ArithMode::Checked), Err(ArithError::Overflow));
+        assert_eq!(b.arith(ArithOp::Sub, a, ArithMode::Wrapping), Ok(U8Cell(156)));
+        assert_eq!(b.arith(ArithOp::Sub, a, ArithMode::Saturating), Ok(U8Cell(0)));
+        assert_eq!(a.arith(ArithOp::Div, b, ArithMode::Checked), Ok(U8Cell(2)));
+        for mode in [ArithMode::Wrapping, ArithMode::Saturating, ArithMode::Checked] {
+            assert_eq!(a.arith(ArithOp::Div, U8Cell(0), mode), Err(ArithError::DivisionByZero));
         }
     }
 
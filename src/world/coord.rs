@@ -3,13 +3,15 @@
 
 use crate::world::{ Adj, Dir };
 use core::fmt;
+use core::str::FromStr;
+use core::num::ParseIntError;
 use core::ops::{ Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Neg, Index, IndexMut };
 
 
 /// A position in a [`World`].
 /// 
 /// Positive direction is right/up-left.
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
 pub struct Coord {
     /// Number of cells to the right direction.
     r  : isize,
@@ -33,6 +35,41 @@ impl Coord {
     /// One unit down-right.
     pub const DR : Self = Self { r : 0, ul : -1 };
 
+    /// The furthest the world head may get from the origin, along either axis.
+    ///
+    /// *Note: This leaves room to find the neighbours and absolute position of any coordinate in range,
+    /// and the offset between any two, without overflowing.*
+    pub const MAX_DISTANCE : isize = isize::MAX / 8;
+
+    /// Create a coordinate from a number of cells in the right and up-left directions.
+    pub fn new(r : isize, ul : isize) -> Self { Self { r, ul } }
+
+    /// Number of cells to the right direction.
+    pub fn r(&self) -> isize { self.r }
+    /// Number of cells to the up-left direction.
+    pub fn ul(&self) -> isize { self.ul }
+
+    /// Returns `true` if neither component is further than [`Self::MAX_DISTANCE`] from the origin.
+    pub fn in_range(&self) -> bool {
+        self.r.unsigned_abs() <= Self::MAX_DISTANCE as usize && self.ul.unsigned_abs() <= Self::MAX_DISTANCE as usize
+    }
+
+    /// Adds two coordinates.
+    ///
+    /// ### Returns
+    /// Returns `None` if either component overflows.
+    pub fn checked_add(self, rhs : Self) -> Option<Self> {
+        Some(Self { r : self.r.checked_add(rhs.r)?, ul : self.ul.checked_add(rhs.ul)? })
+    }
+
+    /// Multiplies both components by a scalar.
+    ///
+    /// ### Returns
+    /// Returns `None` if either component overflows.
+    pub fn checked_mul(self, rhs : isize) -> Option<Self> {
+        Some(Self { r : self.r.checked_mul(rhs)?, ul : self.ul.checked_mul(rhs)? })
+    }
+
     /// Whether this Coord is to the left or right of the origin.
     pub fn half_side(&self) -> Option<Dir> {
         match (self.absolute_x()) {
@@ -60,6 +97,43 @@ impl fmt::Display for Coord {
     }
 }
 
+/// Parses either the [`Display`](fmt::Display) form `(r:R,ul:UL)`, or the short form `R,UL`.
+impl FromStr for Coord {
+    type Err = ParseCoordError;
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')).unwrap_or(s);
+        let (r, ul,) = s.split_once(',').ok_or(ParseCoordError::MissingComma)?;
+        let r  = r.trim();
+        let ul = ul.trim();
+        let r  = r.strip_prefix("r:").unwrap_or(r).trim();
+        let ul = ul.strip_prefix("ul:").unwrap_or(ul).trim();
+        Ok(Self { r : r.parse()?, ul : ul.parse()? })
+    }
+}
+
+/// A [`Coord`] could not be parsed.
+#[derive(Debug)]
+pub enum ParseCoordError {
+    /// The two components were not separated by a comma.
+    MissingComma,
+    /// A component was not an integer.
+    BadInt(ParseIntError)
+}
+
+impl fmt::Display for ParseCoordError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { match (self) {
+        Self::MissingComma => write!(f, "expected a coordinate like `r,ul`"),
+        Self::BadInt(err)  => write!(f, "bad coordinate component: {err}")
+    } }
+}
+
+impl core::error::Error for ParseCoordError { }
+
+impl From<ParseIntError> for ParseCoordError {
+    fn from(err : ParseIntError) -> Self { Self::BadInt(err) }
+}
+
 impl Add<Self> for Coord {
     type Output = Self;
     fn add(self, rhs : Self) -> Self::Output {
@@ -165,6 +239,17 @@ mod tests {
         assert_eq!(Coord { r :  3, ul : 31 } * -4, Coord { r : -12, ul : -124 });
     }
 
+    #[test]
+    fn checked_coords() {
+        assert_eq!(A.checked_add(B), Some(A + B));
+        assert_eq!(A.checked_mul(-4), Some(A * -4));
+        assert_eq!(Coord::new(isize::MAX, 0).checked_add(Coord::R), None);
+        assert_eq!(Coord::UL.checked_mul(isize::MIN), Some(Coord::new(0, isize::MIN)));
+        assert_eq!(Coord::DL.checked_mul(isize::MIN), None);
+        assert!(Coord::new(Coord::MAX_DISTANCE, -Coord::MAX_DISTANCE).in_range());
+        assert!(! Coord::new(0, Coord::MAX_DISTANCE + 1).in_range());
+    }
+
     #[test]
     fn neg_coord() {
         assert_eq!(-Coord { r : 12, ul : 16 }, Coord { r : -12, ul : -16 });
@@ -185,6 +270,15 @@ mod tests {
         assert_eq!(Coord { r :  3, ul : 31 } + Adj::D2,   (Coord { r :  2, ul : 30 }, Coord { r :  3, ul : 30 },));
     }
 
+    #[test]
+    fn parse_coord() {
+        assert_eq!("3,-4".parse::<Coord>().unwrap(), Coord { r : 3, ul : -4 });
+        assert_eq!(" ( r:12, ul:16 ) ".parse::<Coord>().unwrap(), A);
+        assert_eq!(A.to_string().parse::<Coord>().unwrap(), A);
+        assert!(matches!("3".parse::<Coord>(), Err(ParseCoordError::MissingComma)));
+        assert!(matches!("3,x".parse::<Coord>(), Err(ParseCoordError::BadInt(_))));
+    }
+
     #[test]
     fn add_coord_adj_dir() {
         // assert_eq!(Coord { r : })
@@ -2,29 +2,140 @@
 
 
 use crate::world::{ World, Cell, Coord };
+use crate::world::cell::CellValue;
+use crate::json::{ JsonStr, JsonCoord };
 use core::fmt;
 
 
+/// How a [`World`] is displayed.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum WorldFormat {
+
+    /// A grid of cells, with the head and origin highlighted using ANSI colours.
+    #[default]
+    Color,
+
+    /// A grid of cells without any escape codes.
+    ///
+    /// The head is marked with a `*` after its value, the origin with an `o`,
+    /// and the head at the origin with an `@`.
+    Plain,
+
+    /// A single line JSON object, holding the head, the origin, and every cell which is not [`C::ONE`](Cell::ONE).
+    Json
+
+}
+
+
 impl<C : Cell> fmt::Display for World<C> {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { self.fmt_area(f, None) }
+}
+
+
+/// Displays the cells of a [`World`] within some distance of its head, in the display format of the world.
+///
+/// Created by [`World::around_head`].
+pub struct AroundHead<'l, C : Cell> {
+
+    /// The world shown.
+    world  : &'l World<C>,
+
+    /// The number of cells shown on each side of the head.
+    radius : usize
+
+}
+
+impl<C : Cell> fmt::Display for AroundHead<'_, C> {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result { self.world.fmt_area(f, Some(self.radius)) }
+}
+
+
+/// An area of the grid, in absolute positions.
+struct GridArea {
+    /// The leftmost x position.
+    min_x : isize,
+    /// The rightmost x position.
+    max_x : isize,
+    /// The bottom y position.
+    min_y : isize,
+    /// The top y position.
+    max_y : isize
+}
+
+impl GridArea {
+
+    /// Returns `true` if `coord` is inside of this area.
+    fn contains(&self, coord : Coord) -> bool {
+        (self.min_x..=self.max_x).contains(&coord.absolute_x()) && (self.min_y..=self.max_y).contains(&coord.absolute_y())
+    }
+
+}
+
+/// Shows the state of the world, leaving out the input and output streams.
+impl<C : Cell> fmt::Debug for World<C> {
     fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("World")
+            .field("head", &self.head)
+            .field("cells", &self.cells())
+            .field("storage", &self.storage())
+            .field("eof_policy", &self.eof_policy)
+            .field("unread", &self.unread)
+            .field("display_margin", &self.display_margin)
+            .field("display_format", &self.display_format)
+            .field("display_chars", &self.display_chars)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C : Cell> World<C> {
+
+    /// Gets the text shown for a cell in the grid.
+    fn cell_text(&self, cell : &C) -> String {
+        match (self.display_chars.then(|| cell_char(cell)).flatten()) {
+            Some(ch) => format!("'{ch}'"),
+            None     => CellValue(cell).to_string()
+        }
+    }
+
+    /// Displays only the cells within `radius` of the head, in the same way as the whole world is displayed.
+    pub fn around_head(&self, radius : usize) -> AroundHead<'_, C> { AroundHead { world : self, radius } }
+
+    /// Writes the world in its display format, either whole or only the cells within `radius` of the head.
+    fn fmt_area(&self, f : &mut fmt::Formatter<'_>, radius : Option<usize>) -> fmt::Result {
+        let area = radius.map(|radius| {
+            let (x, y, radius,) = (self.head.absolute_x(), self.head.absolute_y(), radius as isize,);
+            GridArea { min_x : x - (radius * 2), max_x : x + (radius * 2), min_y : y - radius, max_y : y + radius }
+        });
+        match (self.display_format) {
+            WorldFormat::Color => self.fmt_grid(f, true, area),
+            WorldFormat::Plain => self.fmt_grid(f, false, area),
+            WorldFormat::Json  => self.fmt_json(f, area)
+        }
+    }
+
+    /// Writes the world as a grid of cells, optionally using ANSI colours.
+    ///
+    /// Shows `area` if given, or otherwise every used cell with the display margin around them.
+    fn fmt_grid(&self, f : &mut fmt::Formatter<'_>, color : bool, area : Option<GridArea>) -> fmt::Result {
+        let esc = |code : &'static str| if (color) { code } else { "" };
+
         let margin = self.display_margin as isize;
-        let mut min_x     = -margin;
-        let mut max_x     =  margin;
-        let mut min_y     = -margin;
-        let mut max_y     =  margin;
-        let mut max_value =  0usize;
-        for (coord, value) in self.cells.iter().chain([(&self.head, self.cells.get(&self.head).unwrap_or(&C::ONE))]) {
+        let mut used = GridArea { min_x : -margin, max_x : margin, min_y : -margin, max_y : margin };
+        let mut value_len = 1usize;
+        let head_value = self.peek(self.head);
+        for (coord, value) in self.cells.cells().into_iter().chain([(self.head, &head_value)]) {
+            if (area.as_ref().is_some_and(|area| ! area.contains(coord))) { continue; }
             let x = coord.absolute_x();
             let y = coord.absolute_y();
-            min_x = min_x.min(x - margin);
-            max_x = max_x.max(x + margin);
-            min_y = min_y.min(y - margin);
-            max_y = max_y.max(y + margin);
-            max_value = max_value.max(value.get_usize_val());
+            used.min_x = used.min_x.min(x - margin);
+            used.max_x = used.max_x.max(x + margin);
+            used.min_y = used.min_y.min(y - margin);
+            used.max_y = used.max_y.max(y + margin);
+            value_len = value_len.max(self.cell_text(value).chars().count());
         }
-        let value_len = (max_value.checked_ilog10()).map_or(1, |v| (v as usize) + 1);
+        let GridArea { min_x, max_x, min_y, max_y } = area.unwrap_or(used);
 
-        writeln!(f, "\x1b[95m\x1b[1mWORLD\x1b[0m")?;
+        writeln!(f, "{}{}WORLD{}", esc("\x1b[95m"), esc("\x1b[1m"), esc("\x1b[0m"))?;
         for y in (min_y..=max_y).rev() {
             write!(f, "| ")?;
             let first_x = min_x.rem_euclid(2) != y.rem_euclid(2);
@@ -32,23 +143,131 @@ impl<C : Cell> fmt::Display for World<C> {
             let mut x = min_x + (first_x as isize);
             while (x <= max_x) {
                 let coord = Coord::from_absolute(x, y);
-                let cell  = self.cells.get(&coord).cloned().unwrap_or(C::ONE).get_usize_val();
-                match ((coord == Coord::ZERO, coord == self.head)) {
-                    (true, true) => { write!(f, "\x1b[93m\x1b[1m")?; },
-                    (true, false) => { write!(f, "\x1b[91m\x1b[1m")?; },
-                    (false, true) => { write!(f, "\x1b[92m\x1b[1m")?; },
-                    (false, false) => { }
+                let cell  = self.peek(coord);
+                let cell  = self.cell_text(&cell);
+                if (color) {
+                    match (coord == Coord::ZERO, coord == self.head) {
+                        (true, true) => { write!(f, "\x1b[93m\x1b[1m")?; },
+                        (true, false) => { write!(f, "\x1b[91m\x1b[1m")?; },
+                        (false, true) => { write!(f, "\x1b[92m\x1b[1m")?; },
+                        (false, false) => { }
+                    }
+                    write!(f, "{: >value_len$}{: >value_len$}", cell, "")?;
+                    write!(f, "\x1b[0m")?;
+                } else {
+                    let marker = match (coord == Coord::ZERO, coord == self.head) {
+                        (true, true) => '@',
+                        (true, false) => 'o',
+                        (false, true) => '*',
+                        (false, false) => ' '
+                    };
+                    write!(f, "{: >value_len$}{marker}{: >pad$}", cell, "", pad = value_len - 1)?;
                 }
-                if (coord == Coord::ZERO) {  }
-                write!(f, "{: >value_len$}{: >value_len$}", cell, "")?;
-                write!(f, "\x1b[0m")?;
                 x += 2;
             }
             writeln!(f)?;
         }
-        write!(f, "\x1b[94mBL{:-<width$}\x1b[0m", Coord::from_absolute(min_x, min_y), width = 2 + ((max_x.abs_diff(min_x) + 1) * value_len))?;
-        write!(f, " | \x1b[91m\x1b[1mORIGIN{}\x1b[0m", Coord::ZERO)?;
-        write!(f, " | \x1b[92m\x1b[1mHEAD{}\x1b[0m", self.head)?;
+        write!(f, "{}BL{:-<width$}{}", esc("\x1b[94m"), Coord::from_absolute(min_x, min_y), esc("\x1b[0m"), width = 2 + ((max_x.abs_diff(min_x) + 1) * value_len))?;
+        write!(f, " | {}{}ORIGIN{}{}", esc("\x1b[91m"), esc("\x1b[1m"), Coord::ZERO, esc("\x1b[0m"))?;
+        write!(f, " | {}{}HEAD{}{}", esc("\x1b[92m"), esc("\x1b[1m"), self.head, esc("\x1b[0m"))?;
         Ok(())
     }
+
+    /// Writes the world as a single line JSON object, holding only the cells in `area` if given.
+    fn fmt_json(&self, f : &mut fmt::Formatter<'_>, area : Option<GridArea>) -> fmt::Result {
+        write!(f, "{{\"head\":{},\"origin\":{},\"cells\":[", JsonCoord(self.head), JsonCoord(Coord::ZERO))?;
+        let cells = self.cells.cells().into_iter().filter(|(coord, _,)| area.as_ref().is_none_or(|area| area.contains(*coord)));
+        for (i, (coord, cell,),) in cells.enumerate() {
+            if (i > 0) { write!(f, ",")?; }
+            write!(f, "{{\"coord\":{},\"value\":{}", JsonCoord(coord), CellValue(cell))?;
+            if (self.display_chars) && let Some(ch) = cell_char(cell) {
+                write!(f, ",\"char\":{}", JsonStr(ch.encode_utf8(&mut [0; 4])))?;
+            }
+            write!(f, "}}")?;
+        }
+        write!(f, "]}}")
+    }
+
+}
+
+/// Gets the printable character a cell would write to the output stream, if any.
+fn cell_char<C : Cell>(cell : &C) -> Option<char> {
+    let text      = cell.to_string();
+    let mut chars = text.chars();
+    match (chars.next(), chars.next(),) {
+        (Some(ch), None,) if (! ch.is_control()) => Some(ch),
+        _ => None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::cell::U32Cell;
+    use std::io;
+
+    /// Creates a small world with a few cells set.
+    fn world() -> World<U32Cell> {
+        let mut world = World::with_io(io::empty(), io::sink());
+        world.poke(Coord::new(1, 0), U32Cell::from('a'));
+        world.poke(Coord::new(0, 1), U32Cell::from('\n'));
+        *world.head_mut() = Coord::new(1, 0);
+        world.set_display_margin(0);
+        world
+    }
+
+    #[test]
+    fn plain() {
+        let mut world = world();
+        world.set_display_format(WorldFormat::Plain);
+        assert_eq!(world.to_string(), concat!(
+            "WORLD\n",
+            "| 10   1  \n",
+            "|    1o 97* \n",
+            "BL(r:0,ul:0) | ORIGIN(r:0,ul:0) | HEAD(r:1,ul:0)"
+        ));
+        world.set_display_chars(true);
+        assert_eq!(world.to_string().lines().nth(2), Some("|      1o  'a'*  "));
+    }
+
+    #[test]
+    fn around_head() {
+        let mut world = world();
+        world.set_display_format(WorldFormat::Plain);
+        *world.head_mut() = Coord::new(2, 0);
+        assert_eq!(world.around_head(1).to_string(), concat!(
+            "WORLD\n",
+            "|    1   1  \n",
+            "| 97   1*  1  \n",
+            "|    1   1  \n",
+            "BL(r:0,ul:-1) | ORIGIN(r:0,ul:0) | HEAD(r:2,ul:0)"
+        ));
+        world.set_display_format(WorldFormat::Json);
+        assert_eq!(
+            world.around_head(1).to_string(),
+            "{\"head\":{\"r\":2,\"ul\":0},\"origin\":{\"r\":0,\"ul\":0},\"cells\":[{\"coord\":{\"r\":1,\"ul\":0},\"value\":97}]}"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let mut world = world();
+        world.set_display_format(WorldFormat::Json);
+        world.set_display_chars(true);
+        assert_eq!(world.to_string(), concat!(
+            "{\"head\":{\"r\":1,\"ul\":0},\"origin\":{\"r\":0,\"ul\":0},\"cells\":[",
+            "{\"coord\":{\"r\":0,\"ul\":1},\"value\":10},",
+            "{\"coord\":{\"r\":1,\"ul\":0},\"value\":97,\"char\":\"a\"}",
+            "]}"
+        ));
+    }
+
+    #[test]
+    fn debug() {
+        let debug = format!("{:?}", world());
+        assert!(debug.starts_with("World { head: Coord { r: 1, ul: 0 }, cells: [("));
+        assert!(debug.ends_with(", .. }"));
+    }
+
 }
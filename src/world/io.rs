@@ -0,0 +1,35 @@
+//! Input and output streams for Worlds.
+
+
+use std::cell::RefCell;
+use std::io::{ self, Write };
+use std::rc::Rc;
+
+
+/// An output stream which writes to a shared in-memory buffer.
+///
+/// Clones of this share the same buffer, so one clone can be given to a
+/// [`World`](crate::world::World) while another is used to inspect what was written.
+#[derive(Clone, Default, Debug)]
+pub struct MemoryOutput(Rc<RefCell<Vec<u8>>>);
+
+impl MemoryOutput {
+
+    /// Get a copy of everything written so far.
+    pub fn contents(&self) -> Vec<u8> { self.0.borrow().clone() }
+
+    /// Take everything written so far, leaving the buffer empty.
+    pub fn take(&self) -> Vec<u8> { self.0.take() }
+
+}
+
+impl Write for MemoryOutput {
+
+    fn write(&mut self, buf : &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+
+}
@@ -2,11 +2,10 @@
 
 
 use core::ops::{ Deref, DerefMut };
-use core::hint;
-use std::collections::BTreeMap;
+use std::io::{ Read, BufReader, Write };
 
 mod coord;
-pub use coord::Coord;
+pub use coord::{ Coord, ParseCoordError };
 
 mod adj;
 pub use adj::Adj;
@@ -16,46 +15,105 @@ pub use dir::Dir;
 
 
 pub mod cell;
-pub use cell::{ Cell, CellStdinReadError };
+pub use cell::{ Cell, ArithOp, ArithMode, ArithError, CellReadError };
+
+mod io;
+pub use io::MemoryOutput;
+
+mod storage;
+pub use storage::WorldStorage;
+use storage::CellStorage;
 
 mod fmt;
+pub use fmt::{ WorldFormat, AroundHead };
 
 
 /// A container for the cell grid and world head.
-#[derive(Debug)]
 pub struct World<C : Cell> {
 
     /// The current position of the world head.
     head           : Coord,
 
     /// The cells in the world.
-    cells          : BTreeMap<Coord, C>,
+    cells          : CellStorage<C>,
+
+    /// The stream read from when the origin is read from its right.
+    input          : Box<dyn Read>,
+
+    /// The stream written to when the origin is written to from its left.
+    output         : Box<dyn Write>,
+
+    /// What happens when the origin is read after the end of the input stream.
+    eof_policy     : EofPolicy,
 
-    /// Standard in data.
-    stdin          : C::StdinReader,
+    /// Cells given back to the input stream, to be read again before it, last first.
+    unread         : Vec<C>,
 
-    #[cfg(debug_assertions)]
-    /// The display margin.
-    display_margin : usize
+    /// The number of cells shown around the used area when the world is displayed.
+    display_margin : usize,
+
+    /// How the world is displayed.
+    display_format : WorldFormat,
+
+    /// Whether cells are displayed as the characters they would write, where printable.
+    display_chars  : bool
 
 }
 
+/// Creates a world which reads from stdin and writes to stdout.
 impl<C : Cell> Default for World<C> {
-    fn default() -> Self { Self {
-        head           : Coord::ZERO,
-        cells          : BTreeMap::new(),
-        stdin          : C::create_stdin_reader(),
-        #[cfg(debug_assertions)]
-        display_margin : 1
-    } }
+    fn default() -> Self {
+        Self::with_io(std::io::stdin(), std::io::stdout())
+    }
 }
 
 impl<C : Cell> World<C> {
 
+    /// Creates an empty world which reads from `input` and writes to `output`.
+    pub fn with_io<R, W>(input : R, output : W) -> Self
+    where
+        R : Read + 'static,
+        W : Write + 'static
+    { Self {
+        head           : Coord::ZERO,
+        cells          : CellStorage::new(WorldStorage::default()),
+        input          : Box::new(BufReader::new(input)),
+        output         : Box::new(output),
+        eof_policy     : EofPolicy::default(),
+        unread         : Vec::new(),
+        display_margin : 1,
+        display_format : WorldFormat::default(),
+        display_chars  : false
+    } }
+
     /// Sets the display margin.
-    #[cfg(debug_assertions)]
     pub fn set_display_margin(&mut self, margin : usize) { self.display_margin = margin; }
 
+    /// Sets how the world is displayed.
+    pub fn set_display_format(&mut self, format : WorldFormat) { self.display_format = format; }
+
+    /// Sets whether cells are displayed as the characters they would write, where printable.
+    pub fn set_display_chars(&mut self, chars : bool) { self.display_chars = chars; }
+
+    /// Sets how the cells of the world are stored, moving any existing cells into the new storage.
+    pub fn set_storage(&mut self, kind : WorldStorage) {
+        if (kind == self.cells.kind()) { return; }
+        let mut cells = CellStorage::new(kind);
+        for (coord, cell,) in self.cells.cells() {
+            cells.set(coord, cell.clone());
+        }
+        self.cells = cells;
+    }
+
+    /// Get how the cells of the world are stored.
+    pub fn storage(&self) -> WorldStorage { self.cells.kind() }
+
+    /// Sets what happens when the origin is read after the end of the input stream.
+    pub fn set_eof_policy(&mut self, eof_policy : EofPolicy) { self.eof_policy = eof_policy; }
+
+    /// Get what happens when the origin is read after the end of the input stream.
+    pub fn eof_policy(&self) -> EofPolicy { self.eof_policy }
+
     /// Get the current world head.
     pub fn head(&self) -> Coord {
         self.head
@@ -67,52 +125,113 @@ impl<C : Cell> World<C> {
 
     /// Get a cell in the world by coordinate.
     ///
-    /// *Note: If the cell does not exist, [`C::default()*](Default::default) is returned.
-    pub fn get(&mut self, coord : Coord) -> C {
+    /// *Note: If the cell does not exist, [`C::ONE`](Cell::ONE) is returned.*
+    ///
+    /// ### Returns
+    /// Returns:
+    /// - `Ok(_)` with the value of the cell.
+    /// - `Err(_)` if the origin was read from the input stream, and reading failed.
+    /// - `Err(CellReadError::Eof)` if the origin was read after the end of the input stream,
+    ///   and the [`EofPolicy`] does not provide a value.
+    pub fn get(&mut self, coord : Coord) -> Result<C, CellReadError> {
         if (coord == Coord::ZERO) {
             if let Some(Dir::R) = self.head.half_side() {
-                loop {
-                    if let Some(Ok(ch)) = self.stdin.next() {
-                        return ch;
+                if let Some(cell) = self.unread.pop() { return Ok(cell); }
+                match (C::read_from(&mut self.input)) {
+                    Some(cell) => cell,
+                    None       => match (self.eof_policy) {
+                        EofPolicy::Zero                    => Ok(C::ZERO),
+                        EofPolicy::Sentinel                => Ok(C::EOF),
+                        EofPolicy::Halt | EofPolicy::Error => Err(CellReadError::Eof)
                     }
-                    hint::spin_loop();
                 }
             } else {
-                C::ONE
+                Ok(C::ONE)
             }
         } else {
-            self.cells.get(&coord).cloned().unwrap_or(C::ONE)
+            Ok(self.peek(coord))
         }
     }
 
+    /// Get the value stored in a cell, without reading from the input stream.
+    ///
+    /// *Note: If the cell does not exist, or is the origin, [`C::ONE`](Cell::ONE) is returned.*
+    pub fn peek(&self, coord : Coord) -> C {
+        self.cells.get(coord).cloned().unwrap_or(C::ONE)
+    }
+
+    /// Overwrites the value stored in a cell, without writing to the output stream.
+    ///
+    /// *Note: Writes to the origin are ignored.*
+    pub fn poke(&mut self, coord : Coord, cell : C) {
+        if (coord != Coord::ZERO) { self.cells.set(coord, cell); }
+    }
+
+    /// Returns `true` if reading `coord` with the current head would read from the input stream.
+    pub fn reads_input(&self, coord : Coord) -> bool {
+        coord == Coord::ZERO && self.head.half_side() == Some(Dir::R)
+    }
+
+    /// Returns `true` if writing `coord` with the current head would write to the output stream.
+    pub fn writes_output(&self, coord : Coord) -> bool {
+        coord == Coord::ZERO && self.head.half_side() == Some(Dir::L)
+    }
+
+    /// Get every cell which does not hold [`C::ONE`](Cell::ONE), sorted by [`Coord`].
+    pub fn cells(&self) -> Vec<(Coord, &C,)> { self.cells.cells() }
+
+    /// Gives a cell back to the input stream, so that it is read again before anything else.
+    pub fn unread(&mut self, cell : C) { self.unread.push(cell); }
+
     /// Get a mutable reference to a cell in the world by coordinate.
-    pub fn get_mut(&mut self, coord : Coord) -> CellMut<C> {
-        CellMut {
-            cell  : self.get(coord),
+    ///
+    /// ### Returns
+    /// Returns `Err(_)` if the origin was read from the input stream, and reading failed.
+    pub fn get_mut(&mut self, coord : Coord) -> Result<CellMut<'_, C>, CellReadError> {
+        Ok(CellMut {
+            cell  : self.get(coord)?,
             world : self,
             coord
-        }
+        })
     }
 
     /// Overwrites a cell in the world.
-    pub fn insert(&mut self, coord : Coord, cell : C) {
+    ///
+    /// ### Returns
+    /// Returns `Err(_)` if the origin was written to the output stream, and writing failed.
+    pub fn insert(&mut self, coord : Coord, cell : C) -> std::io::Result<()> {
         if (coord == Coord::ZERO) {
             if let Some(Dir::L) = self.head.half_side() {
-                print!("{}", cell);
+                write!(self.output, "{}", cell)?;
             }
-        } else if (cell == C::ONE) {
-            self.cells.remove(&coord);
         } else {
-            self.cells.insert(coord, cell);
+            self.cells.set(coord, cell);
         }
+        Ok(())
     }
     
 }
 
 
+/// What happens when the origin is read after the end of the input stream.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum EofPolicy {
+    /// A cell containing zero is read.
+    Zero,
+    /// A cell containing [`Cell::EOF`] is read.
+    Sentinel,
+    /// The script stops running, as if it had reached its end.
+    #[default]
+    Halt,
+    /// The script stops with an error.
+    Error
+}
+
+
 /// Mutable access to a cell in a [`World`].
 ///
-/// *Note: Changes to the [`World`] are applied when this is dropped.*
+/// *Note: Changes to the [`World`] are applied when this is dropped, and any error
+/// writing to the output stream is ignored.*
 pub struct CellMut<'l, C : Cell> {
 
     /// The current value of the cell, not yet written to the [`World`].
@@ -141,7 +260,7 @@ impl<'l, C : Cell> DerefMut for CellMut<'l, C> {
 
 impl<'l, C : Cell> Drop for CellMut<'l, C> {
     fn drop(&mut self) {
-        self.world.insert(self.coord, self.cell);
+        let _ = self.world.insert(self.coord, self.cell.clone());
     }
 }
 
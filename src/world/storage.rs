@@ -0,0 +1,222 @@
+//! Storage for the cells of Worlds.
+
+
+use crate::world::{ Cell, Coord };
+use std::collections::{ BTreeMap, HashMap };
+use std::hash::{ BuildHasherDefault, Hasher };
+
+
+/// How the cells of a [`World`](crate::world::World) are stored.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum WorldStorage {
+
+    /// Dense square chunks of cells in a hash map. Fast for cells which are close together.
+    #[default]
+    Chunked,
+
+    /// A sorted map of individual cells. Uses less memory for cells which are far apart.
+    Sparse
+
+}
+
+
+/// The cells of a world. Absent cells hold [`C::ONE`](Cell::ONE).
+pub(super) enum CellStorage<C : Cell> {
+    /// See [`WorldStorage::Chunked`].
+    Chunked(ChunkedCells<C>),
+    /// See [`WorldStorage::Sparse`].
+    Sparse(BTreeMap<Coord, C>)
+}
+
+impl<C : Cell> CellStorage<C> {
+
+    /// Creates empty storage of the given kind.
+    pub(super) fn new(kind : WorldStorage) -> Self { match (kind) {
+        WorldStorage::Chunked => Self::Chunked(ChunkedCells::default()),
+        WorldStorage::Sparse  => Self::Sparse(BTreeMap::new())
+    } }
+
+    /// Get the kind of this storage.
+    pub(super) fn kind(&self) -> WorldStorage { match (self) {
+        Self::Chunked(_) => WorldStorage::Chunked,
+        Self::Sparse(_)  => WorldStorage::Sparse
+    } }
+
+    /// Get the value of a cell, or `None` if it holds [`C::ONE`](Cell::ONE).
+    #[inline]
+    pub(super) fn get(&self, coord : Coord) -> Option<&C> { match (self) {
+        Self::Chunked(cells) => cells.get(coord),
+        Self::Sparse(cells)  => cells.get(&coord)
+    } }
+
+    /// Overwrites the value of a cell.
+    #[inline]
+    pub(super) fn set(&mut self, coord : Coord, cell : C) { match (self) {
+        Self::Chunked(cells) => cells.set(coord, cell),
+        Self::Sparse(cells)  => {
+            if (cell == C::ONE) { cells.remove(&coord); }
+            else { cells.insert(coord, cell); }
+        }
+    } }
+
+    /// Get every cell which does not hold [`C::ONE`](Cell::ONE), sorted by [`Coord`].
+    pub(super) fn cells(&self) -> Vec<(Coord, &C,)> { match (self) {
+        Self::Chunked(cells) => {
+            let mut cells = cells.iter().collect::<Vec<_>>();
+            cells.sort_unstable_by_key(|(coord, _,)| *coord);
+            cells
+        },
+        Self::Sparse(cells) => cells.iter().map(|(coord, cell,)| (*coord, cell,)).collect()
+    } }
+
+}
+
+
+/// The number of bits of each [`Coord`] component addressing a cell within its chunk.
+const CHUNK_BITS : u32   = 4;
+
+/// The width and height of each chunk.
+const CHUNK_SIZE : isize = 1 << CHUNK_BITS;
+
+/// Dense square chunks of cells in a hash map.
+///
+/// Chunks are dropped once every cell in them holds [`C::ONE`](Cell::ONE) again.
+pub(super) struct ChunkedCells<C : Cell> {
+
+    /// The chunks, by the position of the chunk.
+    chunks : HashMap<(isize, isize,), Chunk<C>, BuildHasherDefault<ChunkHasher>>
+
+}
+
+/// A dense square of cells.
+struct Chunk<C : Cell> {
+
+    /// The cells, row by row.
+    cells : Box<[C]>,
+
+    /// The number of cells which do not hold [`C::ONE`](Cell::ONE).
+    used  : usize
+
+}
+
+impl<C : Cell> Default for ChunkedCells<C> {
+    fn default() -> Self { Self { chunks : HashMap::default() } }
+}
+
+impl<C : Cell> ChunkedCells<C> {
+
+    /// Splits a coordinate into the position of its chunk, and its index within the chunk.
+    #[inline]
+    fn split(coord : Coord) -> ((isize, isize,), usize,) {
+        let (r, ul,) = (coord.r(), coord.ul(),);
+        let index = ((r & (CHUNK_SIZE - 1)) << CHUNK_BITS) | (ul & (CHUNK_SIZE - 1));
+        ((r >> CHUNK_BITS, ul >> CHUNK_BITS,), index as usize,)
+    }
+
+    /// Get the value of a cell, or `None` if it holds [`C::ONE`](Cell::ONE).
+    #[inline]
+    fn get(&self, coord : Coord) -> Option<&C> {
+        let (key, index,) = Self::split(coord);
+        let cell = &self.chunks.get(&key)?.cells[index];
+        (*cell != C::ONE).then_some(cell)
+    }
+
+    /// Overwrites the value of a cell.
+    #[inline]
+    fn set(&mut self, coord : Coord, cell : C) {
+        let (key, index,) = Self::split(coord);
+        let is_one = cell == C::ONE;
+        let chunk = if (is_one) {
+            let Some(chunk) = self.chunks.get_mut(&key)
+                else { return; };
+            chunk
+        } else {
+            self.chunks.entry(key).or_insert_with(|| Chunk {
+                cells : vec![C::ONE; (CHUNK_SIZE * CHUNK_SIZE) as usize].into_boxed_slice(),
+                used  : 0
+            })
+        };
+        let was_one = chunk.cells[index] == C::ONE;
+        chunk.cells[index] = cell;
+        match (was_one, is_one,) {
+            (true, false,) => { chunk.used += 1; },
+            (false, true,) => {
+                chunk.used -= 1;
+                if (chunk.used == 0) { self.chunks.remove(&key); }
+            },
+            _ => { }
+        }
+    }
+
+    /// Iterates over every cell which does not hold [`C::ONE`](Cell::ONE), in no particular order.
+    fn iter(&self) -> impl Iterator<Item = (Coord, &C,)> {
+        self.chunks.iter().flat_map(|((chunk_r, chunk_ul,), chunk,)| {
+            chunk.cells.iter().enumerate()
+                .filter(|(_, cell,)| **cell != C::ONE)
+                .map(move |(index, cell,)| {
+                    let index = index as isize;
+                    let r  = (chunk_r  << CHUNK_BITS) | (index >> CHUNK_BITS);
+                    let ul = (chunk_ul << CHUNK_BITS) | (index & (CHUNK_SIZE - 1));
+                    (Coord::new(r, ul), cell,)
+                })
+        })
+    }
+
+}
+
+
+/// A fast hasher for chunk positions, which are small and not attacker controlled.
+#[derive(Default)]
+struct ChunkHasher(u64);
+
+impl Hasher for ChunkHasher {
+
+    fn finish(&self) -> u64 { self.0 }
+
+    fn write(&mut self, bytes : &[u8]) {
+        for &b in bytes { self.write_u64(b as u64); }
+    }
+
+    fn write_u64(&mut self, n : u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x51_7C_C1_B7_27_22_0A_95);
+    }
+
+    fn write_usize(&mut self, n : usize) { self.write_u64(n as u64); }
+
+    fn write_isize(&mut self, n : isize) { self.write_u64(n as u64); }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::cell::U32Cell;
+
+    #[test]
+    fn storages_agree() {
+        let coords = [
+            Coord::new(0, 0), Coord::new(-1, 0), Coord::new(15, 16), Coord::new(-17, -16),
+            Coord::new(1000, -3), Coord::new(-1, -1), Coord::new(15, 16)
+        ];
+        let mut chunked = CellStorage::<U32Cell>::new(WorldStorage::Chunked);
+        let mut sparse  = CellStorage::<U32Cell>::new(WorldStorage::Sparse);
+        for (i, coord,) in coords.iter().enumerate() {
+            let cell = U32Cell::from(char::from(b'a' + i as u8));
+            chunked.set(*coord, cell);
+            sparse.set(*coord, cell);
+        }
+        assert_eq!(chunked.cells(), sparse.cells());
+        assert_eq!(chunked.cells().len(), 6);
+        for coord in &coords[..6] {
+            let coord = *coord;
+            assert_eq!(chunked.get(coord), sparse.get(coord));
+            chunked.set(coord, U32Cell::ONE);
+        }
+        assert_eq!(chunked.get(Coord::new(2, 2)), None);
+        let CellStorage::Chunked(cells) = chunked
+            else { unreachable!() };
+        assert!(cells.chunks.is_empty());
+    }
+
+}